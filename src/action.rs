@@ -0,0 +1,385 @@
+//! Actions shared by keybinds, buttons, the mouse and `mplay ctl`.
+//!
+//! An action is written as a name with an optional argument: `toggle`,
+//! `seek(+30s)`, `seek_percent(25)`, `volume(-10%)`, `set_volume(50%)` or
+//! `switch_player(next)`. `volume=-10%` is the same as `volume(-10%)`.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Something the user can ask mplay or the player to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Action {
+    /// Exit mplay
+    Quit,
+    Suspend,
+    Toggle,
+    Next,
+    Prev,
+    /// Seek by this many seconds, backwards if negative
+    Seek(f64),
+    /// Seek to a percentage of the track
+    SeekPercent(f64),
+    /// Change the volume by this many percentage points
+    Volume(f64),
+    /// Set the volume to a percentage
+    SetVolume(f64),
+    ToggleShuffle,
+    CycleLoop,
+    RateUp,
+    RateDown,
+    RateReset,
+    QueueUp,
+    QueueDown,
+    QueuePlay,
+    QueueRemove,
+    SwitchPlayer(PlayerTarget),
+    PlayerPicker,
+    PlaylistBrowser,
+    /// Prompt for a URI or path to open in the player
+    OpenUri,
+    /// Same prompt, starting in the file browser
+    OpenFile,
+    /// Bring the player's window to the front
+    Raise,
+    /// Ask the player itself to exit
+    QuitPlayer,
+    ToggleFullscreen,
+    /// Temporarily lock the current player when following activity
+    PinPlayer,
+    /// Look for a player again
+    Reconnect,
+}
+
+/// Player for `switch_player`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerTarget {
+    Next,
+    Prev,
+    /// Bus name or identity
+    Named(String),
+}
+
+impl Action {
+    /// Actions without an argument, by name
+    fn from_name(name: &str) -> Option<Self> {
+        let action = match name {
+            "quit" => Action::Quit,
+            "suspend" => Action::Suspend,
+            "toggle" => Action::Toggle,
+            "next" => Action::Next,
+            "prev" => Action::Prev,
+            "toggle_shuffle" => Action::ToggleShuffle,
+            "cycle_loop" => Action::CycleLoop,
+            "rate_up" => Action::RateUp,
+            "rate_down" => Action::RateDown,
+            "rate_reset" => Action::RateReset,
+            "queue_up" => Action::QueueUp,
+            "queue_down" => Action::QueueDown,
+            "queue_play" => Action::QueuePlay,
+            "queue_remove" => Action::QueueRemove,
+            "player_picker" => Action::PlayerPicker,
+            "playlist_browser" => Action::PlaylistBrowser,
+            "open_uri" => Action::OpenUri,
+            "open_file" => Action::OpenFile,
+            "raise" => Action::Raise,
+            "quit_player" => Action::QuitPlayer,
+            "toggle_fullscreen" => Action::ToggleFullscreen,
+            "pin_player" => Action::PinPlayer,
+            "reconnect" => Action::Reconnect,
+
+            // Names from before actions took arguments
+            "seek_forward" => Action::Seek(5.0),
+            "seek_backward" => Action::Seek(-5.0),
+            "volume_up" | "volumeup" => Action::Volume(5.0),
+            "volume_down" | "volumedown" => Action::Volume(-5.0),
+            "next_player" => Action::SwitchPlayer(PlayerTarget::Next),
+            "prev_player" => Action::SwitchPlayer(PlayerTarget::Prev),
+            "shuffle" => Action::ToggleShuffle,
+            "loop" => Action::CycleLoop,
+            "rateup" => Action::RateUp,
+            "ratedown" => Action::RateDown,
+            "ratereset" => Action::RateReset,
+            "fullscreen" => Action::ToggleFullscreen,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// Name of an action without an argument
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Suspend => "suspend",
+            Action::Toggle => "toggle",
+            Action::Next => "next",
+            Action::Prev => "prev",
+            Action::Seek(_) => "seek",
+            Action::SeekPercent(_) => "seek_percent",
+            Action::Volume(_) => "volume",
+            Action::SetVolume(_) => "set_volume",
+            Action::ToggleShuffle => "toggle_shuffle",
+            Action::CycleLoop => "cycle_loop",
+            Action::RateUp => "rate_up",
+            Action::RateDown => "rate_down",
+            Action::RateReset => "rate_reset",
+            Action::QueueUp => "queue_up",
+            Action::QueueDown => "queue_down",
+            Action::QueuePlay => "queue_play",
+            Action::QueueRemove => "queue_remove",
+            Action::SwitchPlayer(_) => "switch_player",
+            Action::PlayerPicker => "player_picker",
+            Action::PlaylistBrowser => "playlist_browser",
+            Action::OpenUri => "open_uri",
+            Action::OpenFile => "open_file",
+            Action::Raise => "raise",
+            Action::QuitPlayer => "quit_player",
+            Action::ToggleFullscreen => "toggle_fullscreen",
+            Action::PinPlayer => "pin_player",
+            Action::Reconnect => "reconnect",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, arg) = match s
+            .strip_suffix(')')
+            .and_then(|rest| rest.split_once('('))
+            .or_else(|| s.split_once('='))
+        {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (s, None),
+        };
+
+        let example = match name {
+            "seek" => "seek(+30s)",
+            "seek_percent" => "seek_percent(25)",
+            "volume" => "volume(-10%)",
+            "set_volume" => "set_volume(50%)",
+            "switch_player" => "switch_player(next)",
+            _ => {
+                let action = Self::from_name(name).ok_or_else(|| anyhow!("Unknown action '{}'", s))?;
+                if arg.is_some() {
+                    bail!("'{}' takes no argument", name);
+                }
+                return Ok(action);
+            }
+        };
+
+        let Some(arg) = arg.filter(|arg| !arg.is_empty()) else {
+            bail!("'{}' needs an argument, e.g. {}", name, example);
+        };
+        let invalid = || anyhow!("Invalid argument '{}' for {}, expected e.g. {}", arg, name, example);
+
+        let action = match name {
+            "seek" => Action::Seek(parse_offset(arg).ok_or_else(invalid)?),
+            "seek_percent" => Action::SeekPercent(
+                parse_percent(arg).filter(|p| (0.0..=100.0).contains(p)).ok_or_else(invalid)?,
+            ),
+            "volume" => Action::Volume(parse_percent(arg).ok_or_else(invalid)?),
+            "set_volume" => Action::SetVolume(parse_percent(arg).filter(|p| *p >= 0.0).ok_or_else(invalid)?),
+            _ => Action::SwitchPlayer(match arg {
+                "next" => PlayerTarget::Next,
+                "prev" => PlayerTarget::Prev,
+                name => PlayerTarget::Named(name.to_string()),
+            }),
+        };
+        Ok(action)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name();
+        match self {
+            Action::Seek(secs) => write!(f, "{}({:+}s)", name, secs),
+            Action::SeekPercent(percent) => write!(f, "{}({})", name, percent),
+            Action::Volume(percent) => write!(f, "{}({:+}%)", name, percent),
+            Action::SetVolume(percent) => write!(f, "{}({}%)", name, percent),
+            Action::SwitchPlayer(PlayerTarget::Next) => write!(f, "{}(next)", name),
+            Action::SwitchPlayer(PlayerTarget::Prev) => write!(f, "{}(prev)", name),
+            Action::SwitchPlayer(PlayerTarget::Named(player)) => write!(f, "{}({})", name, player),
+            _ => f.write_str(name),
+        }
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Action> for String {
+    fn from(action: Action) -> Self {
+        action.to_string()
+    }
+}
+
+/// Largest seek offset, in seconds, that players can take as i64 microseconds
+const MAX_OFFSET_SECS: f64 = (i64::MAX / 1_000_000) as f64;
+
+/// Parse `+30s`, `-5`, `+1:30` and the like into signed seconds
+pub fn parse_offset(s: &str) -> Option<f64> {
+    let (sign, time) = match s.as_bytes().first()? {
+        b'+' => (1.0, &s[1..]),
+        b'-' => (-1.0, &s[1..]),
+        _ => (1.0, s),
+    };
+    let time = time.strip_suffix('s').unwrap_or(time);
+    let secs = parse_time(time)?.as_secs_f64();
+    (secs <= MAX_OFFSET_SECS).then_some(sign * secs)
+}
+
+/// Parse `50%`, `50`, `+5%` or `-5%` into signed percent
+pub fn parse_percent(s: &str) -> Option<f64> {
+    let number = s.strip_suffix('%').unwrap_or(s).trim();
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, number.strip_prefix('+').unwrap_or(number)),
+    };
+    // Only one sign, which f64 parsing would otherwise take a second of
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let percent: f64 = digits.parse().ok().filter(|p: &f64| p.is_finite())?;
+    Some(sign * percent)
+}
+
+/// Parse `SS`, `MM:SS` or `HH:MM:SS`; seconds may have a fraction
+pub fn parse_time(s: &str) -> Option<Duration> {
+    let mut parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let secs: f64 = parts.pop()?.parse().ok()?;
+    if !secs.is_finite() || secs < 0.0 || (!parts.is_empty() && secs >= 60.0) {
+        return None;
+    }
+
+    let mut total = secs;
+    for (i, (part, scale)) in parts.iter().rev().zip([60.0, 3600.0]).enumerate() {
+        let value: u32 = part.parse().ok()?;
+        // Minutes only wrap into hours when an hour field is present
+        if i == 0 && parts.len() == 2 && value >= 60 {
+            return None;
+        }
+        total += f64::from(value) * scale;
+    }
+    Duration::try_from_secs_f64(total).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Action {
+        s.parse().unwrap_or_else(|e| panic!("{:?} failed to parse: {:#}", s, e))
+    }
+
+    fn error(s: &str) -> String {
+        format!("{:#}", s.parse::<Action>().unwrap_err())
+    }
+
+    #[test]
+    fn parses_arguments() {
+        assert_eq!(parse("seek(+1:00)"), Action::Seek(60.0));
+        assert_eq!(parse("seek(-5s)"), Action::Seek(-5.0));
+        assert_eq!(parse("seek( 90 )"), Action::Seek(90.0));
+        assert_eq!(parse("seek_percent(25)"), Action::SeekPercent(25.0));
+        assert_eq!(parse("volume=-5%"), Action::Volume(-5.0));
+        assert_eq!(parse("volume(+10%)"), Action::Volume(10.0));
+        assert_eq!(parse("set_volume(50%)"), Action::SetVolume(50.0));
+        assert_eq!(parse("set_volume=50"), Action::SetVolume(50.0));
+        assert_eq!(parse("switch_player(next)"), Action::SwitchPlayer(PlayerTarget::Next));
+        assert_eq!(parse("switch_player(prev)"), Action::SwitchPlayer(PlayerTarget::Prev));
+        assert_eq!(
+            parse("switch_player(vlc)"),
+            Action::SwitchPlayer(PlayerTarget::Named("vlc".into()))
+        );
+        assert_eq!(parse(" toggle "), Action::Toggle);
+    }
+
+    #[test]
+    fn accepts_older_names() {
+        assert_eq!(parse("seek_forward"), Action::Seek(5.0));
+        assert_eq!(parse("seek_backward"), Action::Seek(-5.0));
+        assert_eq!(parse("volume_down"), Action::Volume(-5.0));
+        assert_eq!(parse("volumeup"), Action::Volume(5.0));
+        assert_eq!(parse("next_player"), Action::SwitchPlayer(PlayerTarget::Next));
+        assert_eq!(parse("shuffle"), Action::ToggleShuffle);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(error("dance"), "Unknown action 'dance'");
+        assert_eq!(error("toggle(1)"), "'toggle' takes no argument");
+        assert_eq!(error("seek"), "'seek' needs an argument, e.g. seek(+30s)");
+        assert_eq!(error("volume()"), "'volume' needs an argument, e.g. volume(-10%)");
+        assert_eq!(
+            error("seek(soon)"),
+            "Invalid argument 'soon' for seek, expected e.g. seek(+30s)"
+        );
+        for bad in [
+            "seek(1:75)",
+            "seek(+-5)",
+            "seek(1e300)",
+            "seek(+1e300s)",
+            "seek(1e18)",
+            "seek_percent(150)",
+            "set_volume(-5%)",
+            "volume(5%%)",
+            "volume(inf)",
+            "volume(+-5%)",
+        ] {
+            assert!(bad.parse::<Action>().is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let actions = [
+            Action::Toggle,
+            Action::Seek(30.0),
+            Action::Seek(-2.5),
+            Action::SeekPercent(25.0),
+            Action::Volume(-10.0),
+            Action::SetVolume(50.0),
+            Action::SwitchPlayer(PlayerTarget::Next),
+            Action::SwitchPlayer(PlayerTarget::Named("vlc".into())),
+            Action::Reconnect,
+        ];
+        for action in actions {
+            assert_eq!(parse(&action.to_string()), action);
+        }
+        assert_eq!(Action::Seek(30.0).to_string(), "seek(+30s)");
+        assert_eq!(Action::Volume(-10.0).to_string(), "volume(-10%)");
+        assert_eq!(parse("volume_up").to_string(), "volume(+5%)");
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_time("1:30"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_time("1:00:00.5"), Some(Duration::from_millis(3_600_500)));
+        assert_eq!(parse_time("1:60"), None);
+        assert_eq!(parse_time("1:99:00"), None);
+        assert_eq!(parse_time("99:00"), Some(Duration::from_secs(5940)));
+        assert_eq!(parse_time("1e300"), None);
+        assert_eq!(parse_time("99999999999999999999"), None);
+        assert_eq!(parse_time("-1"), None);
+        assert_eq!(parse_time("1:2:3:4"), None);
+        assert_eq!(parse_time(""), None);
+    }
+}
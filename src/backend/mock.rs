@@ -0,0 +1,274 @@
+//! In-memory backend for driving the UI in tests
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::{unsupported, PlayerBackend};
+use crate::mpris_client::{Capabilities, PlayerEvent, PlayerState, Status, Track};
+
+/// Control call received by a `MockBackend`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    Connect,
+    Toggle,
+    Next,
+    Prev,
+    SeekForward(Duration),
+    SeekBackward(Duration),
+    SetPosition(Duration),
+    AdjustVolume(f64),
+    SetVolume(f64),
+    ToggleShuffle,
+    CycleLoop,
+    AdjustRate(f64),
+    ResetRate,
+    GoTo(usize),
+    RemoveTrack(usize),
+}
+
+type Update = Box<dyn FnOnce(&mut PlayerState)>;
+
+/// Shared between a `MockBackend` and the test that owns it
+#[derive(Default)]
+struct Shared {
+    calls: Vec<Call>,
+    /// Scripted state changes, applied one per `process_events`
+    updates: VecDeque<Update>,
+    /// Whether the backend claims to emit signals
+    signals: bool,
+}
+
+/// Test-side handle for scripting a `MockBackend` and inspecting its calls
+#[derive(Clone, Default)]
+pub struct MockHandle {
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl MockHandle {
+    /// Calls received so far
+    pub fn calls(&self) -> Vec<Call> {
+        self.shared.borrow().calls.clone()
+    }
+
+    /// Queue a change the backend reports on the next `process_events`,
+    /// as if the player had signalled it
+    pub fn push_update(&self, update: impl FnOnce(&mut PlayerState) + 'static) {
+        self.shared.borrow_mut().updates.push_back(Box::new(update));
+    }
+
+    /// Queue a signal the backend applies on the next `process_events`,
+    /// the way the MPRIS backend applies it to its cached state
+    pub fn push_event(&self, event: PlayerEvent) {
+        self.push_update(move |state| {
+            state.apply_event(event);
+        });
+    }
+
+    /// Behave like a player with signals, so the UI only sees changes
+    /// through `process_events` instead of re-reading state after actions
+    pub fn emit_signals(&self) {
+        self.shared.borrow_mut().signals = true;
+    }
+}
+
+/// Backend with an in-memory player that obeys controls immediately
+pub struct MockBackend {
+    state: PlayerState,
+    handle: MockHandle,
+}
+
+impl MockBackend {
+    /// A backend whose player starts in `state`
+    pub fn new(state: PlayerState) -> (Self, MockHandle) {
+        let handle = MockHandle::default();
+        let backend = Self {
+            state,
+            handle: handle.clone(),
+        };
+        (backend, handle)
+    }
+
+    /// A connected, fully capable player paused on a three-minute track
+    pub fn track_state(title: &str) -> PlayerState {
+        PlayerState {
+            connected: true,
+            player_name: "Mock".into(),
+            title: title.into(),
+            artists: "Mock Artist".into(),
+            album: "Mock Album".into(),
+            status: Status::Paused,
+            position_sampled_at: Some(Instant::now()),
+            rate: 1.0,
+            min_rate: 0.5,
+            max_rate: 2.0,
+            length: Duration::from_secs(180),
+            volume: 0.5,
+            caps: Capabilities {
+                can_control: true,
+                can_play: true,
+                can_pause: true,
+                can_seek: true,
+                can_go_next: true,
+                can_go_previous: true,
+                has_track_list: true,
+                can_edit_tracks: true,
+                ..Capabilities::default()
+            },
+            tracks: ["One", "Two", "Three"]
+                .iter()
+                .enumerate()
+                .map(|(i, title)| Track {
+                    id: format!("/track/{}", i),
+                    title: title.to_string(),
+                    ..Track::default()
+                })
+                .collect(),
+            ..PlayerState::default()
+        }
+    }
+
+    fn record(&mut self, call: Call) {
+        self.handle.shared.borrow_mut().calls.push(call);
+    }
+
+    /// Record a call, failing with `Unsupported` like a real player would
+    fn require(&mut self, allowed: bool, action: &'static str, call: Call) -> Result<()> {
+        if !allowed {
+            return Err(unsupported(&self.state, action));
+        }
+        self.record(call);
+        Ok(())
+    }
+
+    fn seek_to(&mut self, position: Duration) {
+        self.state.position = position.min(self.state.length);
+        self.state.position_sampled_at = Some(Instant::now());
+    }
+}
+
+impl PlayerBackend for MockBackend {
+    fn connect(&mut self) -> Result<bool> {
+        self.record(Call::Connect);
+        Ok(self.state.connected)
+    }
+
+    fn state(&self) -> &PlayerState {
+        &self.state
+    }
+
+    fn get_state(&mut self) -> PlayerState {
+        self.state.clone()
+    }
+
+    fn refresh_position(&mut self) -> PlayerState {
+        self.state.clone()
+    }
+
+    fn process_events(&mut self) -> bool {
+        let update = self.handle.shared.borrow_mut().updates.pop_front();
+        match update {
+            Some(update) => {
+                update(&mut self.state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Polling unless the test asked for signals
+    fn emits_signals(&self) -> bool {
+        self.handle.shared.borrow().signals
+    }
+
+    fn toggle(&mut self) -> Result<()> {
+        let caps = self.state.caps;
+        self.require(caps.can_play || caps.can_pause, "Play/pause", Call::Toggle)?;
+        self.state.status = match self.state.status {
+            Status::Playing => Status::Paused,
+            _ => Status::Playing,
+        };
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_go_next, "Next track", Call::Next)
+    }
+
+    fn prev(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_go_previous, "Previous track", Call::Prev)
+    }
+
+    fn seek_forward(&mut self, duration: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking", Call::SeekForward(duration))?;
+        self.seek_to(self.state.position + duration);
+        Ok(())
+    }
+
+    fn seek_backward(&mut self, duration: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking", Call::SeekBackward(duration))?;
+        self.seek_to(self.state.position.saturating_sub(duration));
+        Ok(())
+    }
+
+    fn set_position(&mut self, position: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking", Call::SetPosition(position))?;
+        self.seek_to(position);
+        Ok(())
+    }
+
+    fn adjust_volume(&mut self, delta: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control", Call::AdjustVolume(delta))?;
+        self.state.volume = (self.state.volume + delta).clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    fn set_volume(&mut self, level: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control", Call::SetVolume(level))?;
+        self.state.volume = level.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    fn toggle_shuffle(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Shuffle", Call::ToggleShuffle)?;
+        self.state.shuffle = !self.state.shuffle;
+        Ok(())
+    }
+
+    fn cycle_loop(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Loop", Call::CycleLoop)?;
+        self.state.loop_status = self.state.loop_status.next();
+        Ok(())
+    }
+
+    fn adjust_rate(&mut self, delta: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Playback rate", Call::AdjustRate(delta))?;
+        self.state.rate = (self.state.rate + delta).clamp(self.state.min_rate, self.state.max_rate);
+        Ok(())
+    }
+
+    fn reset_rate(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Playback rate", Call::ResetRate)?;
+        self.state.rate = 1.0;
+        Ok(())
+    }
+
+    fn go_to(&mut self, index: usize) -> Result<()> {
+        self.require(self.state.caps.has_track_list, "Track list", Call::GoTo(index))?;
+        if let Some(track) = self.state.tracks.get(index) {
+            self.state.track_id = Some(track.id.clone());
+            self.state.title = track.title.clone();
+        }
+        Ok(())
+    }
+
+    fn remove_track(&mut self, index: usize) -> Result<()> {
+        self.require(self.state.caps.can_edit_tracks, "Removing tracks", Call::RemoveTrack(index))?;
+        if index < self.state.tracks.len() {
+            self.state.tracks.remove(index);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use crate::config::{BackendKind, Config, PlaylistOrder};
+use crate::mpd_client::MpdClient;
+use crate::mpris_client::{MprisClient, PlayerState, PlayerSummary, Playlist, Unsupported};
+
+#[cfg(test)]
+pub mod mock;
+
+/// Source of player state and target of playback controls.
+///
+/// The UI only talks to players through this trait; capabilities travel in
+/// `PlayerState::caps`. Features a backend doesn't have default to reporting
+/// `Unsupported` or an empty result.
+pub trait PlayerBackend {
+    /// Try to connect to a player
+    fn connect(&mut self) -> Result<bool>;
+
+    /// Last known player state
+    fn state(&self) -> &PlayerState;
+
+    /// Re-read the full player state
+    fn get_state(&mut self) -> PlayerState;
+
+    /// Re-sample the playback position to correct interpolation drift
+    fn refresh_position(&mut self) -> PlayerState;
+
+    /// Apply pending change notifications to the cached state.
+    ///
+    /// Returns true if the state changed.
+    fn process_events(&mut self) -> bool;
+
+    /// Whether changes arrive through `process_events`, so polling can stop
+    fn emits_signals(&self) -> bool;
+
+    fn toggle(&mut self) -> Result<()>;
+    fn next(&mut self) -> Result<()>;
+    fn prev(&mut self) -> Result<()>;
+    fn seek_forward(&mut self, duration: Duration) -> Result<()>;
+    fn seek_backward(&mut self, duration: Duration) -> Result<()>;
+    fn set_position(&mut self, position: Duration) -> Result<()>;
+    fn adjust_volume(&mut self, delta: f64) -> Result<()>;
+    /// Set the volume to a level between 0 and 1
+    fn set_volume(&mut self, level: f64) -> Result<()>;
+    fn toggle_shuffle(&mut self) -> Result<()>;
+    fn cycle_loop(&mut self) -> Result<()>;
+    fn adjust_rate(&mut self, delta: f64) -> Result<()>;
+    fn reset_rate(&mut self) -> Result<()>;
+
+    /// Jump to a track in the queue
+    fn go_to(&mut self, index: usize) -> Result<()>;
+
+    /// Remove a track from the queue
+    fn remove_track(&mut self, index: usize) -> Result<()>;
+
+    /// Every player found at the last `refresh_players`
+    fn players(&self) -> &[PlayerSummary] {
+        &[]
+    }
+
+    /// Bus name of the connected player
+    fn active_bus_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Re-scan for players
+    fn refresh_players(&mut self) -> Result<&[PlayerSummary]> {
+        Ok(self.players())
+    }
+
+    /// Switch to a player by bus name or identity
+    fn select_player(&mut self, _name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Switch to the next or previous player
+    fn cycle_player(&mut self, _forward: bool) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Whether automatic player switching is suspended
+    fn is_pinned(&self) -> bool {
+        false
+    }
+
+    /// Toggle the pin; returns the new pinned state
+    fn toggle_pin(&mut self) -> bool {
+        false
+    }
+
+    /// Playlists fetched by the last `refresh_playlists`
+    fn playlists(&self) -> &[Playlist] {
+        &[]
+    }
+
+    /// Fetch up to `max_count` playlists
+    fn refresh_playlists(
+        &mut self,
+        _order: PlaylistOrder,
+        _reverse: bool,
+        _max_count: u32,
+    ) -> Result<&[Playlist]> {
+        Err(unsupported(self.state(), "Playlists"))
+    }
+
+    /// Start playing a playlist from the last `refresh_playlists`
+    fn activate_playlist(&mut self, _index: usize) -> Result<()> {
+        Err(unsupported(self.state(), "Playlists"))
+    }
+
+    /// Bring the player's user interface to the front
+    fn raise(&mut self) -> Result<()> {
+        Err(unsupported(self.state(), "Raise"))
+    }
+
+    /// Ask the player to exit
+    fn quit_player(&mut self) -> Result<()> {
+        Err(unsupported(self.state(), "Quit"))
+    }
+
+    /// Toggle the player's fullscreen mode
+    fn toggle_fullscreen(&mut self) -> Result<()> {
+        Err(unsupported(self.state(), "Fullscreen"))
+    }
+
+    /// URI schemes `open_uri` accepts
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// MIME types `open_uri` accepts
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Ask the player to open and play a URI
+    fn open_uri(&mut self, _uri: &str) -> Result<()> {
+        Err(unsupported(self.state(), "Opening URIs"))
+    }
+}
+
+/// Backend selected by `config.backend`.
+///
+/// `serve` registers the MPRIS mirror, which only makes sense for a
+/// long-running instance.
+pub fn from_config(config: &Config, serve: bool) -> Box<dyn PlayerBackend> {
+    match config.backend {
+        BackendKind::Mpris => Box::new(MprisClient::new(
+            config.players.clone(),
+            config.follow_mode,
+            serve,
+        )),
+        BackendKind::Mpd => Box::new(MpdClient::new(config.mpd.clone())),
+    }
+}
+
+/// `Unsupported` error for the player in `state`
+pub fn unsupported(state: &PlayerState, action: &'static str) -> anyhow::Error {
+    Unsupported {
+        action,
+        player: state.player_name.clone(),
+    }
+    .into()
+}
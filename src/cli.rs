@@ -0,0 +1,508 @@
+//! Headless subcommands for scripts and window manager keybindings
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Parser, Subcommand};
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::action::{parse_offset, parse_percent, parse_time};
+use crate::backend::{self, PlayerBackend};
+use crate::config::Config;
+use crate::control::{self, Request};
+use crate::mpris_client::{format_duration, PlayerState, Status};
+use crate::template::{ParseError, Template};
+
+/// Exit code when a command was understood but failed
+const EXIT_FAILED: u8 = 1;
+/// Exit code when no player could be found
+const EXIT_NO_PLAYER: u8 = 3;
+/// Exit code when `ctl` finds no running instance
+const EXIT_NO_INSTANCE: u8 = 4;
+
+#[derive(Parser)]
+#[command(
+    name = "mplay",
+    version,
+    about = "TUI-based MPRIS music client",
+    after_help = "Without a command, mplay starts the TUI.\n\n\
+                  Exit codes: 0 success, 1 command failed, 2 invalid arguments, 3 no player found, \
+                  4 no running instance (ctl)"
+)]
+pub struct Cli {
+    /// Player to use, by bus name or identity (defaults to the configured players)
+    #[arg(short, long, global = true, value_name = "NAME")]
+    pub player: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show the player's status and current track
+    Status,
+    /// Start playback
+    Play,
+    /// Pause playback
+    Pause,
+    /// Toggle between playing and paused
+    Toggle,
+    /// Skip to the next track
+    Next,
+    /// Go back to the previous track
+    Prev,
+    /// Seek relative to the current position, e.g. +10, -5 or +1:00
+    Seek {
+        #[arg(allow_hyphen_values = true)]
+        offset: String,
+    },
+    /// Jump to a position in the track, e.g. 1:23 or 83
+    Position { position: String },
+    /// Set the volume, e.g. 50%, or change it with +5% / -5%
+    Volume {
+        #[arg(allow_hyphen_values = true)]
+        level: String,
+    },
+    /// List available players; the one that would be used is marked with *
+    Players,
+    /// Print the player state for status bars
+    Print(PrintArgs),
+    /// Send a command to the mplay TUI running on this machine
+    Ctl {
+        #[command(subcommand)]
+        request: Request,
+    },
+}
+
+#[derive(Args)]
+pub struct PrintArgs {
+    /// Output template, e.g. "{artist} - {title|truncate(30)}" or "{position:m:ss}"
+    #[arg(short, long, default_value = "{if artist}{artist} - {end}{title}")]
+    format: Template,
+    /// Tooltip template for --json; \n starts a new line
+    #[arg(long, default_value = "{title}\\n{artist}\\n{album}", value_parser = parse_tooltip)]
+    tooltip: Template,
+    /// Keep running and print a new line whenever the output changes
+    #[arg(long)]
+    follow: bool,
+    /// Print waybar custom module JSON: text, tooltip, class and percentage (track progress)
+    #[arg(long)]
+    json: bool,
+    /// Truncate the text to this many characters
+    #[arg(long, value_name = "N")]
+    max_length: Option<usize>,
+    /// Marks the end of truncated text
+    #[arg(long, default_value = "…")]
+    ellipsis: String,
+}
+
+/// Why a command didn't succeed
+enum Failure {
+    NoPlayer(String),
+    NoInstance(String),
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(err: anyhow::Error) -> Self {
+        Failure::Failed(err)
+    }
+}
+
+impl Failure {
+    /// Exit code reported for this failure
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::NoPlayer(_) => EXIT_NO_PLAYER,
+            Failure::NoInstance(_) => EXIT_NO_INSTANCE,
+            Failure::Failed(_) => EXIT_FAILED,
+        }
+    }
+
+    /// Players couldn't be reached at all (no session bus, MPD down), which
+    /// scripts should treat like no player running
+    fn unreachable(err: anyhow::Error) -> Self {
+        Failure::NoPlayer(format!("{:#}", err))
+    }
+}
+
+/// Run a command against the configured backend and report how it went
+pub fn run(config: &Config, player: Option<&str>, command: Command) -> ExitCode {
+    let result = match command {
+        Command::Print(ref args) if args.follow => {
+            follow(backend::from_config(config, false).as_mut(), player, args)
+        }
+        Command::Ctl { ref request } => ctl(request),
+        command => execute(backend::from_config(config, false).as_mut(), player, command),
+    };
+
+    let Err(failure) = result else {
+        return ExitCode::SUCCESS;
+    };
+    match failure {
+        Failure::NoPlayer(ref message) | Failure::NoInstance(ref message) => {
+            eprintln!("mplay: {}", message);
+        }
+        Failure::Failed(ref err) => eprintln!("mplay: {:#}", err),
+    }
+    ExitCode::from(failure.exit_code())
+}
+
+fn execute(
+    backend: &mut dyn PlayerBackend,
+    player: Option<&str>,
+    command: Command,
+) -> Result<(), Failure> {
+    // Listing works without a player, so it can report that there are none
+    if let Command::Players = command {
+        return list_players(backend, player);
+    }
+
+    let state = connect(backend, player)?;
+
+    match command {
+        Command::Status => {
+            let _ = write_status(&mut io::stdout().lock(), &state);
+        }
+        Command::Print(args) => {
+            // A closed stdout leaves nobody to report to
+            let _ = writeln!(io::stdout(), "{}", render(&args, &state));
+        }
+        // Talks to a running instance rather than a player
+        Command::Ctl { .. } => unreachable!("ctl is handled by run"),
+        Command::Play => {
+            if state.status != Status::Playing {
+                backend.toggle()?;
+            }
+        }
+        Command::Pause => {
+            if state.status == Status::Playing {
+                backend.toggle()?;
+            }
+        }
+        Command::Toggle => backend.toggle()?,
+        Command::Next => backend.next()?,
+        Command::Prev => backend.prev()?,
+        Command::Seek { offset } => {
+            let secs = parse_offset(&offset)
+                .ok_or_else(|| anyhow!("Invalid seek offset '{}'", offset))?;
+            let amount = Duration::from_secs_f64(secs.abs());
+            if secs < 0.0 {
+                backend.seek_backward(amount)?;
+            } else {
+                backend.seek_forward(amount)?;
+            }
+        }
+        Command::Position { position } => {
+            let position = parse_time(&position)
+                .ok_or_else(|| anyhow!("Invalid position '{}'", position))?;
+            backend.set_position(position)?;
+        }
+        Command::Volume { level } => {
+            let target = parse_volume(&level, state.volume)
+                .ok_or_else(|| anyhow!("Invalid volume '{}'", level))?;
+            backend.set_volume(target)?;
+        }
+        Command::Players => unreachable!("players is handled above"),
+    }
+
+    Ok(())
+}
+
+/// List every player, marking the one other commands would use
+fn list_players(backend: &mut dyn PlayerBackend, player: Option<&str>) -> Result<(), Failure> {
+    let state = match connect(backend, player) {
+        Ok(state) => state,
+        Err(Failure::NoPlayer(_)) => PlayerState::default(),
+        Err(err) => return Err(err),
+    };
+
+    let active = backend.active_bus_name().map(str::to_string);
+    let players = backend.refresh_players().map_err(Failure::unreachable)?;
+    let mut stdout = io::stdout().lock();
+    if players.is_empty() && state.connected {
+        // Backends without a player list still have the one they talk to
+        let _ = writeln!(stdout, "* {}", state.player_name);
+    }
+    for p in players {
+        let marker = if Some(&p.bus_name) == active.as_ref() { '*' } else { ' ' };
+        let line = format!("{} {}\t{}\t{:?}\t{}", marker, p.bus_name, p.identity, p.status, p.title);
+        if writeln!(stdout, "{}", line).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Forward a request to the running instance and print what it reports
+fn ctl(request: &Request) -> Result<(), Failure> {
+    let stream = control::connect().map_err(|e| {
+        Failure::NoInstance(format!(
+            "No running instance at {}: {}",
+            control::socket_path().display(),
+            e
+        ))
+    })?;
+
+    let response = control::send(stream, request)?;
+    if !response.ok {
+        let error = response.error.unwrap_or_else(|| "Request failed".into());
+        return Err(Failure::Failed(anyhow!(error)));
+    }
+    if let Some(state) = response.state {
+        let _ = writeln!(io::stdout(), "{}", state);
+    }
+    Ok(())
+}
+
+/// Connect to the requested player, or the one `Config.players` picks
+fn connect(backend: &mut dyn PlayerBackend, player: Option<&str>) -> Result<PlayerState, Failure> {
+    match player {
+        Some(name) => {
+            if !backend.select_player(name).map_err(Failure::unreachable)? {
+                return Err(Failure::NoPlayer(format!("No player matching '{}'", name)));
+            }
+        }
+        None => {
+            backend.connect().map_err(Failure::unreachable)?;
+        }
+    }
+
+    let state = backend.get_state();
+    if !state.connected {
+        return Err(Failure::NoPlayer("No player found".into()));
+    }
+    Ok(state)
+}
+
+/// Print a line for every change until stdout is closed.
+///
+/// A named player is kept even when others start playing, and waited for
+/// while it isn't running.
+fn follow(
+    backend: &mut dyn PlayerBackend,
+    player: Option<&str>,
+    args: &PrintArgs,
+) -> Result<(), Failure> {
+    const TICK: Duration = Duration::from_millis(250);
+    const POLL_RATE: Duration = Duration::from_millis(500);
+    const RESYNC_RATE: Duration = Duration::from_secs(5);
+
+    let mut state = match connect(backend, player) {
+        Ok(state) => state,
+        Err(Failure::NoPlayer(_)) => PlayerState::default(),
+        Err(err) => return Err(err),
+    };
+    if player.is_some() && !backend.is_pinned() {
+        backend.toggle_pin();
+    }
+    let mut last_update = Instant::now();
+    let mut last_line = None;
+    let mut stdout = io::stdout();
+
+    loop {
+        if backend.process_events() {
+            state = backend.state().clone();
+        }
+
+        // Same cadence as the TUI: poll players without signals (or no player
+        // at all), only re-sample the position of those that have them
+        if !backend.emits_signals() || !state.connected {
+            if last_update.elapsed() >= POLL_RATE {
+                state = poll(backend, player);
+                last_update = Instant::now();
+            }
+        } else if last_update.elapsed() >= RESYNC_RATE {
+            state = backend.refresh_position();
+            last_update = Instant::now();
+        }
+
+        let line = render(args, &state);
+        if last_line.as_ref() != Some(&line) {
+            if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+                return Ok(());
+            }
+            last_line = Some(line);
+        }
+
+        thread::sleep(TICK);
+    }
+}
+
+/// Fresh state of the followed player; a named player is looked up again
+/// rather than replaced by whichever one is running
+fn poll(backend: &mut dyn PlayerBackend, player: Option<&str>) -> PlayerState {
+    match player {
+        Some(name) if !backend.select_player(name).unwrap_or(false) => PlayerState::default(),
+        _ => backend.get_state(),
+    }
+}
+
+/// One line of `print` output; empty text while no player is connected
+fn render(args: &PrintArgs, state: &PlayerState) -> String {
+    let expand = |template: &Template| {
+        if state.connected {
+            template.render(state, "")
+        } else {
+            String::new()
+        }
+    };
+
+    let text = truncate(&expand(&args.format), args.max_length, &args.ellipsis);
+    if !args.json {
+        return text;
+    }
+
+    let class = if state.connected {
+        format!("{:?}", state.status).to_lowercase()
+    } else {
+        "disconnected".to_string()
+    };
+    let percentage = if state.length.is_zero() {
+        0
+    } else {
+        (state.current_position().as_secs_f64() / state.length.as_secs_f64() * 100.0).clamp(0.0, 100.0)
+            as u8
+    };
+
+    serde_json::json!({
+        "text": text,
+        "tooltip": expand(&args.tooltip),
+        "class": class,
+        "percentage": percentage,
+    })
+    .to_string()
+}
+
+/// Tooltips are usually passed from a one-line config, so `\n` means a newline
+fn parse_tooltip(s: &str) -> Result<Template, ParseError> {
+    s.replace("\\n", "\n").parse()
+}
+
+/// Cut `text` to `max` characters, ending it with `ellipsis` if anything was cut
+fn truncate(text: &str, max: Option<usize>, ellipsis: &str) -> String {
+    let Some(max) = max else {
+        return text.to_string();
+    };
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let room = max.saturating_sub(ellipsis.chars().count());
+    if room == 0 {
+        return text.chars().take(max).collect();
+    }
+    let mut out: String = text.chars().take(room).collect();
+    out.push_str(ellipsis);
+    out
+}
+
+/// Human-readable summary for `mplay status`
+fn write_status(out: &mut impl Write, state: &PlayerState) -> io::Result<()> {
+    writeln!(out, "Player:   {}", state.player_name)?;
+    writeln!(out, "Status:   {:?}", state.status)?;
+    if !state.title.is_empty() {
+        writeln!(out, "Title:    {}", state.title)?;
+    }
+    if !state.artists.is_empty() {
+        writeln!(out, "Artist:   {}", state.artists)?;
+    }
+    if !state.album.is_empty() {
+        writeln!(out, "Album:    {}", state.album)?;
+    }
+    writeln!(
+        out,
+        "Position: {} / {}",
+        format_duration(state.current_position()),
+        format_duration(state.length)
+    )?;
+    writeln!(out, "Volume:   {}%", (state.volume * 100.0).round() as u8)?;
+    writeln!(
+        out,
+        "Shuffle:  {}  Loop: {:?}",
+        if state.shuffle { "on" } else { "off" },
+        state.loop_status
+    )
+}
+
+/// Parse an absolute (`50%`, `50`) or relative (`+5%`, `-5%`) volume into a
+/// level between 0 and 1, accepting what `set_volume` and `volume` actions do
+fn parse_volume(s: &str, current: f64) -> Option<f64> {
+    let percent = parse_percent(s)?;
+    let level = if s.trim_start().starts_with(['+', '-']) {
+        current + percent / 100.0
+    } else {
+        percent / 100.0
+    };
+    Some(level.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+
+    fn print_args(args: &[&str]) -> PrintArgs {
+        let cli = Cli::try_parse_from(["mplay", "print"].iter().chain(args)).unwrap();
+        match cli.command {
+            Some(Command::Print(args)) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn parses_absolute_and_relative_volume() {
+        assert_eq!(parse_volume("50%", 0.2), Some(0.5));
+        assert_eq!(parse_volume("50", 0.2), Some(0.5));
+        assert_eq!(parse_volume("+25%", 0.5), Some(0.75));
+        assert_eq!(parse_volume("-25%", 0.5), Some(0.25));
+        assert_eq!(parse_volume("150%", 0.2), Some(1.0));
+        assert_eq!(parse_volume("+20%", 0.9), Some(1.0));
+        assert_eq!(parse_volume("-20%", 0.1), Some(0.0));
+        for bad in ["", "loud", "5%%", "inf", "+-5"] {
+            assert_eq!(parse_volume(bad, 0.2), None, "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn truncates_with_ellipsis() {
+        assert_eq!(truncate("Song Title", None, "…"), "Song Title");
+        assert_eq!(truncate("Song Title", Some(10), "…"), "Song Title");
+        assert_eq!(truncate("Song Title", Some(6), "…"), "Song …");
+        assert_eq!(truncate("Song Title", Some(6), "..."), "Son...");
+        // No room for the ellipsis: cut the text hard instead
+        assert_eq!(truncate("Song Title", Some(3), "..."), "Son");
+        assert_eq!(truncate("Song Title", Some(2), "..."), "So");
+    }
+
+    #[test]
+    fn renders_waybar_json() {
+        let args = print_args(&["--json", "--max-length", "12", "--tooltip", "{album}\\n{artist}"]);
+        let state = MockBackend::track_state("Test Title");
+
+        let json: serde_json::Value = serde_json::from_str(&render(&args, &state)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "text": "Mock Artist…",
+                "tooltip": "Mock Album\nMock Artist",
+                "class": "paused",
+                "percentage": 0,
+            })
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render(&args, &PlayerState::default())).unwrap();
+        assert_eq!(json["text"], "");
+        assert_eq!(json["class"], "disconnected");
+    }
+
+    #[test]
+    fn failures_map_to_exit_codes() {
+        assert_eq!(Failure::NoPlayer("none".into()).exit_code(), EXIT_NO_PLAYER);
+        assert_eq!(Failure::NoInstance("none".into()).exit_code(), EXIT_NO_INSTANCE);
+        assert_eq!(Failure::from(anyhow!("broken")).exit_code(), EXIT_FAILED);
+        assert_eq!(Failure::unreachable(anyhow!("no bus")).exit_code(), EXIT_NO_PLAYER);
+    }
+}
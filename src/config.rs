@@ -1,61 +1,352 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::action::{Action, PlayerTarget};
+use crate::template::{Template, TrackTemplate};
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
+    /// How to talk to players
+    pub backend: BackendKind,
+    /// MPD server, used by the `mpd` backend
+    pub mpd: MpdConfig,
     /// Players to try connecting to (in order)
     pub players: Vec<String>,
+    /// How to pick the player to follow when several are running
+    pub follow_mode: FollowMode,
+    /// Register `org.mpris.MediaPlayer2.mplay`, mirroring the followed player
+    pub mpris_server: bool,
     /// Keybindings
     pub keybinds: Keybinds,
     /// Layout configuration
     pub layout: Layout,
+    /// Named alternatives to `layout`, switchable at runtime
+    pub layouts: HashMap<String, Layout>,
     /// Widget configurations
     pub widgets: HashMap<String, WidgetConfig>,
+    /// Transient message (toast) display
+    pub toast: ToastConfig,
+    /// Playlist browser
+    pub playlists: PlaylistsConfig,
+    /// Scroll wheel and right-click menu
+    pub mouse: MouseConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            backend: BackendKind::default(),
+            mpd: MpdConfig::default(),
             players: vec!["spotify".into(), "vlc".into(), "mpd".into()],
+            follow_mode: FollowMode::default(),
+            mpris_server: false,
             keybinds: Keybinds::default(),
             layout: Layout::default(),
+            layouts: HashMap::new(),
             widgets: default_widgets(),
+            toast: ToastConfig::default(),
+            playlists: PlaylistsConfig::default(),
+            mouse: MouseConfig::default(),
         }
     }
 }
 
-/// Keybinding configuration
+/// Player backend
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Any MPRIS player on the session bus
+    #[default]
+    Mpris,
+    /// An MPD server, spoken to directly
+    Mpd,
+}
+
+/// MPD connection settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
-pub struct Keybinds {
-    pub quit: Vec<String>,
-    pub toggle: Vec<String>,
-    pub next: Vec<String>,
-    pub prev: Vec<String>,
-    pub seek_forward: Vec<String>,
-    pub seek_backward: Vec<String>,
-    pub volume_up: Vec<String>,
-    pub volume_down: Vec<String>,
+pub struct MpdConfig {
+    /// Hostname, or an absolute path to a Unix socket
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
 }
 
-impl Default for Keybinds {
+impl Default for MpdConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".into(),
+            port: 6600,
+            password: None,
+        }
+    }
+}
+
+/// Player selection strategy
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FollowMode {
+    /// Stay on the connected player until it goes away
+    Pinned,
+    /// Use the first running player from `players`, switching when one
+    /// listed earlier starts
+    #[default]
+    Preferred,
+    /// Switch to whichever player most recently started playing
+    MostRecentActive,
+}
+
+/// Toast configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToastConfig {
+    pub position: ToastPosition,
+    pub align: Alignment,
+    /// How long a toast stays visible, in milliseconds
+    pub timeout_ms: u64,
+    pub style: StyleConfig,
+}
+
+impl Default for ToastConfig {
+    fn default() -> Self {
+        Self {
+            position: ToastPosition::Bottom,
+            align: Alignment::Left,
+            timeout_ms: 3000,
+            style: StyleConfig { fg: Some("black".into()), bg: Some("yellow".into()), ..Default::default() },
+        }
+    }
+}
+
+/// Screen row used for toasts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToastPosition {
+    Top,
+    #[default]
+    Bottom,
+    /// Only expose the message through `$message`
+    Hidden,
+}
+
+/// Mouse configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseConfig {
+    /// What the wheel does outside progress bars, volume bars and the queue
+    pub scroll: ScrollAction,
+    /// Seek per wheel step, in seconds
+    pub seek_step_secs: u64,
+    /// Volume change per wheel step, in percent
+    pub volume_step_percent: u8,
+    /// Right-click menu entries
+    pub menu: Vec<Action>,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        Self {
+            scroll: ScrollAction::Volume,
+            seek_step_secs: 5,
+            volume_step_percent: 5,
+            menu: vec![
+                Action::Toggle,
+                Action::Next,
+                Action::Prev,
+                Action::ToggleShuffle,
+                Action::CycleLoop,
+                Action::SwitchPlayer(PlayerTarget::Next),
+                Action::PlayerPicker,
+            ],
+        }
+    }
+}
+
+/// Scroll wheel behavior
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollAction {
+    #[default]
+    Volume,
+    Seek,
+    None,
+}
+
+/// Playlist browser configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistsConfig {
+    /// Order requested from the player
+    pub order: PlaylistOrder,
+    pub reverse: bool,
+    /// Maximum number of playlists to fetch
+    pub max_count: u32,
+    /// Glyph shown for playlists that have an icon
+    pub icon: String,
+}
+
+impl Default for PlaylistsConfig {
     fn default() -> Self {
         Self {
-            quit: vec!["q".into(), "Escape".into()],
-            toggle: vec![" ".into()],
-            next: vec!["n".into(), "Right".into()],
-            prev: vec!["p".into(), "Left".into()],
-            seek_forward: vec!["l".into(), "Shift+Right".into()],
-            seek_backward: vec!["h".into(), "Shift+Left".into()],
-            volume_up: vec!["k".into(), "Up".into()],
-            volume_down: vec!["j".into(), "Down".into()],
+            order: PlaylistOrder::default(),
+            reverse: false,
+            max_count: 100,
+            icon: "♫".into(),
+        }
+    }
+}
+
+/// Playlist ordering, as defined by the MPRIS Playlists interface
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistOrder {
+    Alphabetical,
+    Created,
+    Modified,
+    Played,
+    /// The player's own order
+    #[default]
+    User,
+}
+
+/// Keybinding configuration: key -> action, layered over the defaults.
+///
+/// Binding a key to `"none"` removes its default action.
+#[derive(Debug, Clone)]
+pub struct Keybinds(BTreeMap<String, Action>);
+
+/// Keys bound out of the box
+const DEFAULT_KEYBINDS: &[(&str, &str)] = &[
+    ("q", "quit"),
+    ("Escape", "quit"),
+    ("Ctrl+z", "suspend"),
+    (" ", "toggle"),
+    ("n", "next"),
+    ("Right", "next"),
+    ("p", "prev"),
+    ("Left", "prev"),
+    ("l", "seek(+5s)"),
+    ("Shift+Right", "seek(+5s)"),
+    ("h", "seek(-5s)"),
+    ("Shift+Left", "seek(-5s)"),
+    ("k", "volume(+5%)"),
+    ("Up", "volume(+5%)"),
+    ("j", "volume(-5%)"),
+    ("Down", "volume(-5%)"),
+    ("s", "toggle_shuffle"),
+    ("L", "cycle_loop"),
+    ("]", "rate_up"),
+    ("[", "rate_down"),
+    ("=", "rate_reset"),
+    ("K", "queue_up"),
+    ("J", "queue_down"),
+    ("Enter", "queue_play"),
+    ("Delete", "queue_remove"),
+    ("Tab", "switch_player(next)"),
+    ("BackTab", "switch_player(prev)"),
+    ("P", "player_picker"),
+    ("b", "playlist_browser"),
+    ("o", "open_uri"),
+    ("O", "open_file"),
+    ("R", "raise"),
+    ("Q", "quit_player"),
+    ("f", "toggle_fullscreen"),
+    ("Ctrl+p", "pin_player"),
+    ("r", "reconnect"),
+];
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self(
+            DEFAULT_KEYBINDS
+                .iter()
+                .map(|(key, action)| (key.to_string(), action.parse().expect("built-in actions are valid")))
+                .collect(),
+        )
+    }
+}
+
+/// One entry of the `keybinds` object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeybindEntry {
+    /// `"key": "action"`
+    Action(String),
+    /// Older configs: `"action": ["key", ...]`
+    Keys(Vec<String>),
+    /// Older configs: `"select_player": {"key": "player"}`
+    Players(HashMap<String, String>),
+}
+
+impl<'de> Deserialize<'de> for Keybinds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let parse = |key: &str, action: &str| {
+            action
+                .parse::<Action>()
+                .map_err(|e| D::Error::custom(format!("keybind '{}': {:#}", key, e)))
+        };
+
+        let mut keybinds = Self::default();
+        let mut bindings = Vec::new();
+        for (name, entry) in BTreeMap::<String, KeybindEntry>::deserialize(deserializer)? {
+            match entry {
+                KeybindEntry::Action(action) => bindings.push((name, action)),
+                // An old-style list replaces the action's default keys
+                KeybindEntry::Keys(keys) => {
+                    let action = parse(&name, &name)?;
+                    keybinds.0.retain(|_, bound| *bound != action);
+                    for key in keys {
+                        keybinds.0.insert(key, action.clone());
+                    }
+                }
+                KeybindEntry::Players(players) => {
+                    for (key, player) in players {
+                        keybinds.0.insert(key, Action::SwitchPlayer(PlayerTarget::Named(player)));
+                    }
+                }
+            }
         }
+
+        for (key, action) in bindings {
+            if action == "none" {
+                keybinds.0.remove(&key);
+            } else {
+                let action = parse(&key, &action)?;
+                keybinds.0.insert(key, action);
+            }
+        }
+        Ok(keybinds)
+    }
+}
+
+impl Serialize for Keybinds {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        // Unbound defaults are written as "none" so they stay unbound when loaded
+        let unbound = DEFAULT_KEYBINDS
+            .iter()
+            .filter(|(key, _)| !self.0.contains_key(*key))
+            .map(|(key, _)| (key.to_string(), "none".to_string()));
+        let entries: BTreeMap<String, String> = self
+            .0
+            .iter()
+            .map(|(key, action)| (key.clone(), action.to_string()))
+            .chain(unbound)
+            .collect();
+        entries.serialize(serializer)
+    }
+}
+
+impl Keybinds {
+    /// Action bound to `key`
+    pub fn get(&self, key: &str) -> Option<&Action> {
+        self.0.get(key)
     }
 }
 
@@ -135,6 +426,7 @@ pub enum WidgetConfig {
     Volume(VolumeConfig),
     Button(ButtonConfig),
     CoverArt(CoverArtConfig),
+    Queue(QueueConfig),
     Empty(EmptyConfig),
 }
 
@@ -142,17 +434,59 @@ pub enum WidgetConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LabelConfig {
-    pub text: String,
+    /// Template such as `{title|truncate(40)}` or `{if shuffle}🔀{end}`
+    pub text: Template,
     pub align: Alignment,
     pub style: StyleConfig,
+    /// What to do with text wider than the label
+    pub overflow: Overflow,
+    /// Scrolling, for `"overflow": "marquee"`
+    pub marquee: MarqueeConfig,
 }
 
 impl Default for LabelConfig {
     fn default() -> Self {
         Self {
-            text: String::new(),
+            text: Template::default(),
             align: Alignment::Center,
             style: StyleConfig::default(),
+            overflow: Overflow::default(),
+            marquee: MarqueeConfig::default(),
+        }
+    }
+}
+
+/// Handling of label text that doesn't fit
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Cut off at the edge
+    #[default]
+    Clip,
+    /// Cut off with a trailing `…`
+    Ellipsis,
+    /// Scroll through the whole text
+    Marquee,
+}
+
+/// Marquee scrolling configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarqueeConfig {
+    /// Scroll speed in columns per second
+    pub speed: f64,
+    /// How long the text rests with its start, then its end, in view, in milliseconds
+    pub pause_ms: u64,
+    /// Shown between the end of the text and its next repetition
+    pub separator: String,
+}
+
+impl Default for MarqueeConfig {
+    fn default() -> Self {
+        Self {
+            speed: 5.0,
+            pause_ms: 2000,
+            separator: "   ".into(),
         }
     }
 }
@@ -165,6 +499,8 @@ pub struct ProgressConfig {
     pub filled_char: char,
     pub empty_char: char,
     pub style: StyleConfig,
+    /// Style used when the player can't seek
+    pub disabled_style: StyleConfig,
 }
 
 impl Default for ProgressConfig {
@@ -174,6 +510,7 @@ impl Default for ProgressConfig {
             filled_char: '█',
             empty_char: '░',
             style: StyleConfig::default(),
+            disabled_style: StyleConfig::disabled(),
         }
     }
 }
@@ -186,6 +523,8 @@ pub struct VolumeConfig {
     pub filled_char: char,
     pub empty_char: char,
     pub style: StyleConfig,
+    /// Style used when the player can't be controlled
+    pub disabled_style: StyleConfig,
 }
 
 impl Default for VolumeConfig {
@@ -195,6 +534,7 @@ impl Default for VolumeConfig {
             filled_char: '█',
             empty_char: '░',
             style: StyleConfig::default(),
+            disabled_style: StyleConfig::disabled(),
         }
     }
 }
@@ -203,35 +543,32 @@ impl Default for VolumeConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ButtonConfig {
-    pub action: ButtonAction,
-    pub text: String,
+    pub action: Action,
+    pub text: Template,
     pub style: StyleConfig,
+    /// Style used when the player doesn't support the action
+    pub disabled_style: StyleConfig,
+    /// Style while the mouse is over the button; defaults to `style`
+    pub hover_style: Option<StyleConfig>,
+    /// Style while the button is held down; defaults to `hover_style`
+    pub pressed_style: Option<StyleConfig>,
 }
 
 impl Default for ButtonConfig {
     fn default() -> Self {
         Self {
-            action: ButtonAction::Toggle,
-            text: "$status-icon".into(),
+            action: Action::Toggle,
+            text: template("{status-icon}"),
             style: StyleConfig::default(),
+            disabled_style: StyleConfig::disabled(),
+            hover_style: None,
+            pressed_style: None,
         }
     }
 }
 
-/// Button actions
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
-#[serde(rename_all = "lowercase")]
-pub enum ButtonAction {
-    #[default]
-    Toggle,
-    Next,
-    Prev,
-    VolumeUp,
-    VolumeDown,
-}
-
 /// Cover art configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct CoverArtConfig {
     pub width: Option<u16>,
@@ -241,13 +578,28 @@ pub struct CoverArtConfig {
     pub true_color: bool,
 }
 
-impl Default for CoverArtConfig {
+/// Queue (TrackList) widget configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    /// Entry text, a template with `{index}`, `{title}`, `{artists}`,
+    /// `{album}` and `{length}`
+    pub text: TrackTemplate,
+    pub style: StyleConfig,
+    /// Style for the currently playing track
+    pub current_style: StyleConfig,
+    /// Style for the highlighted entry
+    pub selected_style: StyleConfig,
+}
+
+impl Default for QueueConfig {
     fn default() -> Self {
         Self {
-            width: None,
-            height: None,
-            use_ascii: false,
-            true_color: false,
+            text: TrackTemplate::parse("{index}. {title|default(\"Unknown\")}{if artists} - {artists}{end}")
+                .expect("built-in templates are valid"),
+            style: StyleConfig::default(),
+            current_style: StyleConfig { bold: true, ..Default::default() },
+            selected_style: StyleConfig { fg: Some("black".into()), bg: Some("white".into()), ..Default::default() },
         }
     }
 }
@@ -279,6 +631,23 @@ pub struct StyleConfig {
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    pub dim: bool,
+}
+
+impl StyleConfig {
+    /// Default look for widgets whose action the player doesn't support
+    pub fn disabled() -> Self {
+        Self {
+            fg: Some("darkgray".into()),
+            dim: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse a built-in template
+fn template(source: &str) -> Template {
+    Template::parse(source).expect("built-in templates are valid")
 }
 
 /// Create default widget configurations
@@ -286,21 +655,25 @@ fn default_widgets() -> HashMap<String, WidgetConfig> {
     let mut widgets = HashMap::new();
 
     widgets.insert("title".into(), WidgetConfig::Label(LabelConfig {
-        text: "$title".into(),
+        text: template("{title|default(\"Unknown\")}"),
         align: Alignment::Center,
         style: StyleConfig { bold: true, ..Default::default() },
+        overflow: Overflow::Marquee,
+        ..Default::default()
     }));
 
     widgets.insert("artists".into(), WidgetConfig::Label(LabelConfig {
-        text: "$artists".into(),
+        text: template("{artists|default(\"Unknown Artist\")}"),
         align: Alignment::Center,
         style: StyleConfig::default(),
+        ..Default::default()
     }));
 
     widgets.insert("album".into(), WidgetConfig::Label(LabelConfig {
-        text: "$album".into(),
+        text: template("{album|default(\"Unknown Album\")}"),
         align: Alignment::Center,
         style: StyleConfig { italic: true, ..Default::default() },
+        ..Default::default()
     }));
 
     widgets.insert("progress".into(), WidgetConfig::Progress(ProgressConfig {
@@ -309,27 +682,27 @@ fn default_widgets() -> HashMap<String, WidgetConfig> {
     }));
 
     widgets.insert("position".into(), WidgetConfig::Label(LabelConfig {
-        text: "$position".into(),
+        text: template("{position}"),
         align: Alignment::Left,
         style: StyleConfig::default(),
+        ..Default::default()
     }));
 
     widgets.insert("length".into(), WidgetConfig::Label(LabelConfig {
-        text: "$length".into(),
+        text: template("{length}"),
         align: Alignment::Right,
         style: StyleConfig::default(),
+        ..Default::default()
     }));
 
-    widgets.insert("controls".into(), WidgetConfig::Label(LabelConfig {
-        text: "$status-icon".into(),
-        align: Alignment::Center,
-        style: StyleConfig::default(),
-    }));
+    widgets.insert("controls".into(), WidgetConfig::Button(ButtonConfig::default()));
 
     widgets.insert("volume".into(), WidgetConfig::Volume(VolumeConfig::default()));
 
     widgets.insert("cover".into(), WidgetConfig::CoverArt(CoverArtConfig::default()));
 
+    widgets.insert("queue".into(), WidgetConfig::Queue(QueueConfig::default()));
+
     // Flexible spacers for vertical centering
     widgets.insert("spacer_top".into(), WidgetConfig::Empty(EmptyConfig::default()));
     widgets.insert("spacer_bottom".into(), WidgetConfig::Empty(EmptyConfig::default()));
@@ -389,3 +762,62 @@ impl Config {
         Ok(proj_dirs.config_dir().join("config.json"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keybinds(json: &str) -> Keybinds {
+        json5::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn keys_map_to_actions_over_the_defaults() {
+        let keybinds = keybinds(r#"{ "x": "seek(+30s)", "n": "switch_player(next)", "q": "none" }"#);
+
+        assert_eq!(keybinds.get("x"), Some(&Action::Seek(30.0)));
+        assert_eq!(keybinds.get("n"), Some(&Action::SwitchPlayer(PlayerTarget::Next)));
+        assert_eq!(keybinds.get("q"), None);
+        assert_eq!(keybinds.get("Escape"), Some(&Action::Quit));
+    }
+
+    #[test]
+    fn legacy_key_lists_replace_the_default_keys() {
+        let keybinds = keybinds(r#"{ "next": ["N"], "seek_forward": ["."], "volume_up": [] }"#);
+
+        assert_eq!(keybinds.get("N"), Some(&Action::Next));
+        assert_eq!(keybinds.get("n"), None);
+        assert_eq!(keybinds.get("Right"), None);
+        assert_eq!(keybinds.get("."), Some(&Action::Seek(5.0)));
+        assert_eq!(keybinds.get("l"), None);
+        assert_eq!(keybinds.get("k"), None);
+        assert_eq!(keybinds.get("j"), Some(&Action::Volume(-5.0)));
+    }
+
+    #[test]
+    fn legacy_select_player_binds_named_players() {
+        let keybinds = keybinds(r#"{ "select_player": { "1": "vlc", "2": "spotify" }, "2": "toggle" }"#);
+
+        assert_eq!(
+            keybinds.get("1"),
+            Some(&Action::SwitchPlayer(PlayerTarget::Named("vlc".into())))
+        );
+        // Plain entries are applied last
+        assert_eq!(keybinds.get("2"), Some(&Action::Toggle));
+    }
+
+    #[test]
+    fn bad_keybinds_name_the_key() {
+        let err = json5::from_str::<Keybinds>(r#"{ "x": "seek(soon)" }"#).unwrap_err();
+        assert!(err.to_string().contains("keybind 'x': Invalid argument 'soon'"), "{}", err);
+        assert!(json5::from_str::<Keybinds>(r#"{ "dance": ["d"] }"#).is_err());
+    }
+
+    #[test]
+    fn saved_keybinds_load_unchanged() {
+        let keybinds = keybinds(r#"{ "x": "volume(-2.5%)", "q": "none" }"#);
+        let saved = serde_json::to_string(&keybinds).unwrap();
+
+        assert_eq!(json5::from_str::<Keybinds>(&saved).unwrap().0, keybinds.0);
+    }
+}
@@ -0,0 +1,367 @@
+//! Unix socket for driving a running instance with `mplay ctl`.
+//!
+//! Each request is one JSON object on its own line, answered by one JSON
+//! line: `{"command":"action","action":"seek(+30s)"}` gets `{"ok":true}`.
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, DirBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::action::Action;
+
+/// How long `mplay ctl` waits for the instance to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most bytes held for one client, counting unanswered requests and
+/// replies it hasn't read yet; requests are far shorter
+const MAX_BUFFERED: usize = 64 * 1024;
+
+/// Command for a running instance
+#[derive(Debug, Clone, Subcommand, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Switch the followed player, by bus name or identity
+    Player { name: String },
+    /// Show a toast message
+    Toast { text: String },
+    /// Switch to a named layout from the config, or back to default
+    Layout { name: String },
+    /// Run an action, e.g. toggle, seek(+30s) or switch_player(next)
+    Action { action: Action },
+    /// Print the instance's player state as JSON
+    Status,
+}
+
+/// Reply to a `Request`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<serde_json::Value>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self { ok: true, ..Self::default() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn state(state: serde_json::Value) -> Self {
+        Self {
+            ok: true,
+            state: Some(state),
+            ..Self::default()
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/mplay.sock`, or the same name in a per-user directory
+/// in the temp dir
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("mplay.sock"),
+        None => fallback_dir().join("mplay.sock"),
+    }
+}
+
+fn fallback_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("mplay-{}", current_uid()))
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and can't fail
+    unsafe { libc::getuid() }
+}
+
+/// `socket_path`, after making sure nobody else can have put a socket there.
+///
+/// `$XDG_RUNTIME_DIR` is private by definition, but the shared temp dir is
+/// not, so the fallback directory is created 0700 and checked to be ours.
+fn private_socket_path() -> io::Result<PathBuf> {
+    if std::env::var_os("XDG_RUNTIME_DIR").is_none() {
+        let dir = fallback_dir();
+        if let Err(e) = DirBuilder::new().mode(0o700).create(&dir) {
+            if e.kind() != io::ErrorKind::AlreadyExists {
+                return Err(e);
+            }
+        }
+        check_private(&dir)?;
+    }
+    Ok(socket_path())
+}
+
+/// Fail unless `dir` is a real directory owned by us and closed to others
+fn check_private(dir: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != current_uid() || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory of this user", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Listening end, polled from the UI loop without blocking
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+}
+
+/// Connected `mplay ctl`, or any other client
+struct Client {
+    stream: UnixStream,
+    /// Bytes received after the last complete line
+    buffer: Vec<u8>,
+    /// Replies the client hasn't taken yet
+    outgoing: Vec<u8>,
+}
+
+impl ControlServer {
+    /// Listen on `socket_path`, unless another instance already is
+    pub fn bind() -> Result<Self> {
+        let path = private_socket_path().context("Refusing to use the control socket")?;
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("Another instance is listening on {}", path.display());
+        }
+
+        // Left behind by an instance that didn't exit cleanly
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        listener
+            .set_nonblocking(true)
+            .context("Failed to configure control socket")?;
+
+        Ok(Self {
+            listener,
+            path,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Accept new clients and answer every complete request received so far
+    pub fn process(&mut self, mut handle: impl FnMut(Request) -> Response) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    buffer: Vec::new(),
+                    outgoing: Vec::new(),
+                });
+            }
+        }
+
+        self.clients.retain_mut(|client| client.process(&mut handle));
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Client {
+    /// Answer buffered requests; returns false once the client is gone
+    fn process(&mut self, handle: &mut impl FnMut(Request) -> Response) -> bool {
+        let mut chunk = [0; 4096];
+        let mut open = true;
+        // A client that doesn't read its replies isn't read from until it does
+        while self.buffer.len() + self.outgoing.len() < MAX_BUFFERED {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    open = false;
+                    break;
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => {
+                    open = false;
+                    break;
+                }
+            }
+        }
+
+        if self.buffer.len() >= MAX_BUFFERED && !self.buffer.contains(&b'\n') {
+            self.reply(&Response::error("Request too long"));
+            self.flush();
+            return false;
+        }
+
+        // A client may shut down its end right after an unterminated request
+        if !open && !self.buffer.is_empty() && !self.buffer.ends_with(b"\n") {
+            self.buffer.push(b'\n');
+        }
+
+        while self.outgoing.len() < MAX_BUFFERED {
+            let Some(end) = self.buffer.iter().position(|&b| b == b'\n') else {
+                break;
+            };
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str(line.trim()) {
+                Ok(request) => handle(request),
+                Err(e) => Response::error(format!("Invalid request: {}", e)),
+            };
+            self.reply(&response);
+        }
+
+        if !self.flush() {
+            return false;
+        }
+
+        // Stay around to answer what a half-closed client already sent
+        open || !self.outgoing.is_empty() || self.buffer.contains(&b'\n')
+    }
+
+    /// Queue one reply line
+    fn reply(&mut self, response: &Response) {
+        let reply = serde_json::to_string(response).unwrap_or_default();
+        self.outgoing.extend_from_slice(reply.as_bytes());
+        self.outgoing.push(b'\n');
+    }
+
+    /// Write as much of the queued replies as the client takes; returns
+    /// false if the client is gone
+    fn flush(&mut self) -> bool {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Connect to the running instance
+pub fn connect() -> io::Result<UnixStream> {
+    UnixStream::connect(private_socket_path()?)
+}
+
+/// Send one request and wait for the reply
+pub fn send(stream: UnixStream, request: &Request) -> Result<Response> {
+    stream
+        .set_read_timeout(Some(REPLY_TIMEOUT))
+        .context("Failed to configure control socket")?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    (&stream)
+        .write_all(line.as_bytes())
+        .context("Failed to send request")?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("No reply from the running instance")?;
+    serde_json::from_str(&reply).context("Invalid reply from the running instance")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> (Client, UnixStream) {
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        ours.set_nonblocking(true).unwrap();
+        let client = Client {
+            stream: ours,
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+        };
+        (client, theirs)
+    }
+
+    fn read_reply(reader: &mut impl BufRead) -> Response {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn answers_each_line() {
+        let (mut client, mut theirs) = client();
+        theirs.write_all(b"{\"command\":\"status\"}\n{\"command\":\"nope\"}\n").unwrap();
+
+        let mut requests = Vec::new();
+        assert!(client.process(&mut |request| {
+            requests.push(request);
+            Response::ok()
+        }));
+
+        assert_eq!(requests.len(), 1);
+        let mut replies = BufReader::new(theirs);
+        assert!(read_reply(&mut replies).ok);
+        assert!(read_reply(&mut replies).error.unwrap().starts_with("Invalid request"));
+    }
+
+    #[test]
+    fn keeps_replies_for_clients_that_read_slowly() {
+        const REQUESTS: usize = 200;
+        let (mut client, mut theirs) = client();
+        theirs.write_all("{\"command\":\"status\"}\n".repeat(REQUESTS).as_bytes()).unwrap();
+
+        // Far more reply data than the socket buffers
+        let state = serde_json::json!({ "title": "x".repeat(4096) });
+        let mut answer = |_| Response::state(state.clone());
+        // Fill the socket until a reply only goes out in part
+        for _ in 0..REQUESTS {
+            assert!(client.process(&mut answer));
+            if !client.outgoing.is_empty() {
+                break;
+            }
+        }
+        assert!(!client.outgoing.is_empty());
+        assert!(!client.buffer.is_empty());
+
+        let reader = std::thread::spawn(move || {
+            let mut replies = BufReader::new(theirs);
+            (0..REQUESTS).map(|_| read_reply(&mut replies)).collect::<Vec<_>>()
+        });
+        while !reader.is_finished() {
+            assert!(client.process(&mut answer));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let replies = reader.join().unwrap();
+        assert!(replies.iter().all(|reply| reply.state.as_ref() == Some(&state)));
+    }
+
+    #[test]
+    fn drops_clients_that_never_end_a_line() {
+        let (mut client, mut theirs) = client();
+        theirs.write_all(&vec![b'x'; MAX_BUFFERED + 1]).unwrap();
+
+        assert!(!client.process(&mut |_| Response::ok()));
+        let mut replies = BufReader::new(theirs);
+        assert_eq!(read_reply(&mut replies).error.as_deref(), Some("Request too long"));
+    }
+}
@@ -2,6 +2,7 @@ use image::DynamicImage;
 use ratatui_image::{picker::Picker, protocol::StatefulProtocol};
 use std::collections::HashMap;
 use std::io::Read;
+use std::panic;
 use std::path::Path;
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
@@ -55,7 +56,8 @@ impl CoverArtLoader {
         let tx = self.sender.clone();
 
         thread::spawn(move || {
-            let result = load_image(&url_owned);
+            // A decoder panic counts as no cover, so the request still completes
+            let result = panic::catch_unwind(|| load_image(&url_owned)).unwrap_or(None);
             let _ = tx.send((url_owned, result));
         });
     }
@@ -0,0 +1,34 @@
+use directories::ProjectDirs;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Append an error to the log file.
+///
+/// Logging must never take the UI down, so failures here are ignored.
+pub fn error(err: &anyhow::Error) {
+    let Some(path) = log_path() else {
+        return;
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{} ERROR {:#}", timestamp, err);
+    }
+}
+
+/// Get the log file path (state dir, falling back to the cache dir)
+pub fn log_path() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "mplay")?;
+    let dir = proj_dirs.state_dir().unwrap_or(proj_dirs.cache_dir());
+    Some(dir.join("mplay.log"))
+}
@@ -1,19 +1,42 @@
+mod action;
+mod backend;
+mod cli;
 mod config;
+mod control;
 mod cover;
+mod log;
+mod mpd_client;
 mod mpris_client;
+mod mpris_server;
+mod template;
 mod ui;
 
 use anyhow::Result;
+use clap::Parser;
+use cli::Cli;
 use config::Config;
+use std::process::ExitCode;
 use ui::App;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+
     // Load config
-    let config = Config::load()?;
+    let mut config = Config::load()?;
+
+    // Run a single command without the TUI
+    if let Some(command) = cli.command {
+        return Ok(cli::run(&config, cli.player.as_deref(), command));
+    }
+
+    // A player named on the command line goes ahead of the configured ones
+    if let Some(player) = cli.player {
+        config.players.insert(0, player);
+    }
 
     // Create and run app
     let mut app = App::new(config);
     app.run()?;
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,1224 @@
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use mpris::{Metadata, MetadataValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backend::{unsupported, PlayerBackend};
+use crate::config::MpdConfig;
+use crate::mpris_client::{Capabilities, Loop, PlayerState, Status, Track};
+
+/// Idle subsystems that affect what we show
+const WATCHED_SUBSYSTEMS: &[&str] = &["player", "mixer", "options", "playlist"];
+
+/// How long connecting and commands wait for the server, which is called
+/// from the UI thread
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause after a failed connection attempt before trying again
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Largest cover image we accept from the server
+const MAX_PICTURE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Covers kept on disk; the oldest are removed beyond this
+const MAX_CACHED_COVERS: usize = 256;
+
+/// Error reported by the server (an `ACK` line), as opposed to a broken connection
+#[derive(Debug)]
+pub struct MpdError(String);
+
+impl fmt::Display for MpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MPD: {}", self.0)
+    }
+}
+
+impl std::error::Error for MpdError {}
+
+/// Response to a command: `key: value` pairs in order
+type Pairs = Vec<(String, String)>;
+
+/// Socket to the MPD server
+enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn connect(config: &MpdConfig) -> Result<Self> {
+        let stream = if config.host.starts_with('/') {
+            UnixStream::connect(&config.host).map(Stream::Unix)
+        } else {
+            connect_tcp(&config.host, config.port).map(Stream::Tcp)
+        };
+        stream.with_context(|| format!("Failed to connect to MPD at {}", address(config)))
+    }
+
+    /// Limit how long reads and writes block; None blocks indefinitely
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => {
+                s.set_read_timeout(timeout)?;
+                s.set_write_timeout(timeout)
+            }
+            Stream::Unix(s) => {
+                s.set_read_timeout(timeout)?;
+                s.set_write_timeout(timeout)
+            }
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(s) => s.try_clone().map(Stream::Tcp),
+            Stream::Unix(s) => s.try_clone().map(Stream::Unix),
+        }
+    }
+
+    /// Unblock any thread reading from this socket
+    fn shutdown(&self) {
+        let _ = match self {
+            Stream::Tcp(s) => s.shutdown(Shutdown::Both),
+            Stream::Unix(s) => s.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            Stream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            Stream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            Stream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+/// One MPD protocol connection
+struct Connection {
+    reader: BufReader<Stream>,
+    writer: Stream,
+}
+
+impl Connection {
+    /// Connect, check the greeting and authenticate
+    fn open(config: &MpdConfig) -> Result<Self> {
+        let stream = Stream::connect(config)?;
+        stream.set_timeout(Some(TIMEOUT))?;
+        let mut conn = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        let greeting = conn.read_line()?;
+        if !greeting.starts_with("OK MPD ") {
+            anyhow::bail!("Not an MPD server: {}", greeting);
+        }
+
+        if let Some(ref password) = config.password {
+            conn.command(&format!("password {}", quote(password)))?;
+        }
+        Ok(conn)
+    }
+
+    /// Send a command and collect its response
+    fn command(&mut self, command: &str) -> Result<Pairs> {
+        writeln!(self.writer, "{}", command)?;
+        self.writer.flush()?;
+
+        let mut pairs = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(ack) = line.strip_prefix("ACK ") {
+                return Err(MpdError(ack_message(ack).to_string()).into());
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    /// Fetch a whole binary response (`albumart`, `readpicture`) chunk by chunk.
+    ///
+    /// Returns None if the server has no picture for `uri`.
+    fn read_binary(&mut self, command: &str, uri: &str) -> Result<Option<Vec<u8>>> {
+        let mut data = Vec::new();
+        loop {
+            writeln!(self.writer, "{} {} {}", command, quote(uri), data.len())?;
+            self.writer.flush()?;
+
+            let mut size = None;
+            let mut chunk = 0;
+            loop {
+                let line = self.read_line()?;
+                if line == "OK" {
+                    break;
+                }
+                if line.starts_with("ACK ") {
+                    return Ok(None);
+                }
+                match line.split_once(": ") {
+                    Some(("size", value)) => size = value.parse::<usize>().ok(),
+                    Some(("binary", value)) => {
+                        chunk = value.parse().context("Bad binary length from MPD")?;
+                        let start = data.len();
+                        // Never read more than the announced total
+                        let limit = size.unwrap_or(0).min(MAX_PICTURE_SIZE);
+                        if chunk > limit.saturating_sub(start) {
+                            anyhow::bail!("MPD sent more picture data than announced");
+                        }
+                        data.resize(start + chunk, 0);
+                        self.reader.read_exact(&mut data[start..])?;
+                        // The chunk is followed by a newline
+                        self.read_line()?;
+                    }
+                    _ => {}
+                }
+            }
+
+            match size {
+                Some(size) if chunk > 0 && data.len() < size => continue,
+                Some(_) if !data.is_empty() => return Ok(Some(data)),
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("MPD closed the connection");
+        }
+        let len = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(len);
+        Ok(line)
+    }
+}
+
+/// Background connection sitting in `idle`, reporting changed subsystems.
+///
+/// Dropping the handle shuts the socket down, which ends the thread.
+struct IdleThread {
+    receiver: Receiver<Vec<String>>,
+    stream: Stream,
+}
+
+impl IdleThread {
+    fn spawn(config: &MpdConfig) -> Result<Self> {
+        let mut conn = Connection::open(config)?;
+        // Idle waits for changes indefinitely; the reader shares this socket
+        conn.writer.set_timeout(None)?;
+        let stream = conn.writer.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let idle = format!("idle {}", WATCHED_SUBSYSTEMS.join(" "));
+            while let Ok(pairs) = conn.command(&idle) {
+                let changed = pairs
+                    .into_iter()
+                    .filter(|(key, _)| key == "changed")
+                    .map(|(_, value)| value)
+                    .collect();
+                if tx.send(changed).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { receiver: rx, stream })
+    }
+
+    /// Changed subsystems received so far, or None if the thread has died
+    fn drain(&self) -> Option<Vec<String>> {
+        let mut changed = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(subsystems) => changed.extend(subsystems),
+                Err(TryRecvError::Empty) => return Some(changed),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for IdleThread {
+    fn drop(&mut self) {
+        self.stream.shutdown();
+    }
+}
+
+/// Cover art fetched for a song: its `file` and the cached image URL
+type Cover = (String, Option<String>);
+
+/// MPD client for controlling an MPD server directly
+pub struct MpdClient {
+    config: MpdConfig,
+    conn: Option<Connection>,
+    idle: Option<IdleThread>,
+    state: PlayerState,
+    /// Queue version (`playlist` in `status`) the cached tracks belong to
+    queue_version: Option<String>,
+    /// URL schemes (`urlhandlers`) and MIME types (`decoders`) the server plays
+    uri_schemes: Vec<String>,
+    mime_types: Vec<String>,
+    /// Cover for the current song, once fetched
+    cover: Option<Cover>,
+    covers_tx: Sender<Cover>,
+    covers_rx: Receiver<Cover>,
+    /// Earliest time to connect again after a failed attempt
+    retry_at: Option<Instant>,
+}
+
+impl MpdClient {
+    /// Create a new MPD client; connects on first use
+    pub fn new(config: MpdConfig) -> Self {
+        let (covers_tx, covers_rx) = mpsc::channel();
+        Self {
+            config,
+            conn: None,
+            idle: None,
+            state: PlayerState::default(),
+            queue_version: None,
+            uri_schemes: Vec::new(),
+            mime_types: Vec::new(),
+            cover: None,
+            covers_tx,
+            covers_rx,
+            retry_at: None,
+        }
+    }
+
+    /// The open command connection, connecting (and starting idle) if needed
+    fn connection(&mut self) -> Result<&mut Connection> {
+        if self.conn.is_none() {
+            // Each attempt on an unreachable server blocks for up to TIMEOUT
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                anyhow::bail!("Not connected to MPD at {}", address(&self.config));
+            }
+            let mut conn = Connection::open(&self.config)
+                .inspect_err(|_| self.retry_at = Some(Instant::now() + RETRY_INTERVAL))?;
+            self.retry_at = None;
+
+            // A broken connection would leave the reply for the next command
+            let lists = optional(&mut conn, "urlhandlers")
+                .and_then(|handlers| Ok((handlers, optional(&mut conn, "decoders")?)));
+            let (handlers, decoders) = lists.inspect_err(|err| {
+                if is_timeout(err) {
+                    self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+                }
+            })?;
+
+            self.uri_schemes = lookup_all(&handlers, "handler")
+                .into_iter()
+                .map(|h| h.trim_end_matches("://").to_string())
+                .collect();
+            // Local files are only accepted from clients on the Unix socket
+            if self.config.host.starts_with('/') {
+                self.uri_schemes.push("file".into());
+            }
+
+            self.mime_types = lookup_all(&decoders, "mime_type")
+                .into_iter()
+                .map(String::from)
+                .collect();
+            self.mime_types.sort();
+            self.mime_types.dedup();
+
+            // Without idle we fall back to polling
+            self.idle = IdleThread::spawn(&self.config).ok();
+            self.queue_version = None;
+            self.conn = Some(conn);
+        }
+        Ok(self.conn.as_mut().expect("connection was just opened"))
+    }
+
+    /// Run a command, dropping the connection if it broke
+    fn command(&mut self, command: &str) -> Result<Pairs> {
+        let result = self.connection()?.command(command);
+        if let Err(ref err) = result {
+            if err.downcast_ref::<MpdError>().is_none() {
+                // A hung server would otherwise stall every poll
+                if is_timeout(err) {
+                    self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+                }
+                self.disconnect();
+            }
+        }
+        result
+    }
+
+    /// Run a command for its side effect
+    fn run(&mut self, command: &str) -> Result<()> {
+        self.command(command).map(drop)
+    }
+
+    fn disconnect(&mut self) {
+        self.conn = None;
+        self.idle = None;
+    }
+
+    /// Fail with `Unsupported` unless the server allows an action
+    fn require(&self, allowed: bool, action: &'static str) -> Result<()> {
+        if self.state.connected && !allowed {
+            return Err(unsupported(&self.state, action));
+        }
+        Ok(())
+    }
+
+    /// Re-read status, the current song and (if it changed) the queue
+    fn read_state(&mut self) -> Result<()> {
+        let status = self.command("status")?;
+        let song = self.command("currentsong")?;
+
+        let mut state = build_state(&status, &song);
+        let version = lookup(&status, "playlist").map(String::from);
+        if version.is_some() && version == self.queue_version {
+            state.tracks = std::mem::take(&mut self.state.tracks);
+        } else {
+            state.tracks = parse_tracks(&self.command("playlistinfo")?);
+            self.queue_version = version;
+        }
+
+        if let Some(file) = lookup(&song, "file") {
+            match self.cover {
+                Some((ref cover_file, ref url)) if cover_file == file => state.art_url = url.clone(),
+                _ => self.request_cover(file.to_string(), lookup(&song, "Last-Modified")),
+            }
+        }
+
+        self.state = state;
+        Ok(())
+    }
+
+    /// Fetch cover art for `file` in the background, caching it on disk so
+    /// the cover art loader can read it like any other local image.
+    ///
+    /// The cache entry is keyed on the song's modification time, so a
+    /// retagged file gets its cover fetched again.
+    fn request_cover(&mut self, file: String, modified: Option<&str>) {
+        self.cover = Some((file.clone(), None));
+
+        let Some(path) = cover_cache_path(&self.config, &file, modified) else {
+            return;
+        };
+        let url = format!("file://{}", path.display());
+        if path.exists() {
+            self.cover = Some((file, Some(url)));
+            return;
+        }
+
+        let config = self.config.clone();
+        let tx = self.covers_tx.clone();
+        thread::spawn(move || {
+            let fetched = fetch_cover(&config, &file)
+                .ok()
+                .flatten()
+                .and_then(|bytes| store_cover(&path, &bytes).ok());
+            let _ = tx.send((file, fetched.map(|_| url)));
+        });
+    }
+
+    /// Pick up finished cover fetches; returns true if the current song's arrived
+    fn receive_covers(&mut self) -> bool {
+        let mut changed = false;
+        for (file, url) in self.covers_rx.try_iter() {
+            if self.cover.as_ref().is_some_and(|(current, _)| *current == file) {
+                self.state.art_url = url.clone();
+                self.cover = Some((file, url));
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Queue entry at `index`, as an MPD song ID
+    fn song_id(&self, index: usize) -> Option<&str> {
+        self.state.tracks.get(index)?.id.strip_prefix(SONG_ID_PREFIX)
+    }
+}
+
+impl PlayerBackend for MpdClient {
+    fn connect(&mut self) -> Result<bool> {
+        // Asked for explicitly, so don't wait out a previous failure
+        self.retry_at = None;
+        self.connection()?;
+        Ok(true)
+    }
+
+    fn state(&self) -> &PlayerState {
+        &self.state
+    }
+
+    fn get_state(&mut self) -> PlayerState {
+        if self.read_state().is_err() {
+            self.disconnect();
+            self.state = PlayerState::default();
+        }
+        self.state.clone()
+    }
+
+    fn refresh_position(&mut self) -> PlayerState {
+        if let Ok(status) = self.command("status") {
+            if let Some(elapsed) = lookup(&status, "elapsed").and_then(parse_seconds) {
+                self.state.position = elapsed;
+                self.state.position_sampled_at = Some(Instant::now());
+            }
+        }
+        self.state.clone()
+    }
+
+    fn process_events(&mut self) -> bool {
+        let covers_changed = self.receive_covers();
+
+        let changed = match self.idle {
+            Some(ref idle) => idle.drain(),
+            None => return covers_changed,
+        };
+
+        match changed {
+            Some(changed) if changed.is_empty() => covers_changed,
+            Some(_) => {
+                self.get_state();
+                true
+            }
+            // The idle connection died; fall back to polling until reconnected
+            None => {
+                self.idle = None;
+                covers_changed
+            }
+        }
+    }
+
+    fn emits_signals(&self) -> bool {
+        self.idle.is_some()
+    }
+
+    fn toggle(&mut self) -> Result<()> {
+        match self.state.status {
+            Status::Playing => self.run("pause 1"),
+            Status::Paused => self.run("pause 0"),
+            Status::Stopped => self.run("play"),
+        }
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.run("next")
+    }
+
+    fn prev(&mut self) -> Result<()> {
+        self.run("previous")
+    }
+
+    fn seek_forward(&mut self, duration: Duration) -> Result<()> {
+        self.run(&format!("seekcur +{:.3}", duration.as_secs_f64()))
+    }
+
+    fn seek_backward(&mut self, duration: Duration) -> Result<()> {
+        self.run(&format!("seekcur -{:.3}", duration.as_secs_f64()))
+    }
+
+    fn set_position(&mut self, position: Duration) -> Result<()> {
+        self.run(&format!("seekcur {:.3}", position.as_secs_f64()))
+    }
+
+    fn adjust_volume(&mut self, delta: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control")?;
+        self.set_volume(self.state.volume + delta)
+    }
+
+    fn set_volume(&mut self, level: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control")?;
+        self.run(&format!("setvol {}", (level.clamp(0.0, 1.0) * 100.0).round()))
+    }
+
+    fn toggle_shuffle(&mut self) -> Result<()> {
+        self.run(&format!("random {}", u8::from(!self.state.shuffle)))
+    }
+
+    fn cycle_loop(&mut self) -> Result<()> {
+        // MPD's single mode repeats the current song when repeat is on
+        let (repeat, single) = match self.state.loop_status.next() {
+            Loop::None => (0, 0),
+            Loop::Track => (1, 1),
+            Loop::Playlist => (1, 0),
+        };
+        self.run(&format!("repeat {}", repeat))?;
+        self.run(&format!("single {}", single))
+    }
+
+    fn adjust_rate(&mut self, _delta: f64) -> Result<()> {
+        Err(unsupported(&self.state, "Playback rate"))
+    }
+
+    fn reset_rate(&mut self) -> Result<()> {
+        Err(unsupported(&self.state, "Playback rate"))
+    }
+
+    fn go_to(&mut self, index: usize) -> Result<()> {
+        match self.song_id(index).map(String::from) {
+            Some(id) => self.run(&format!("playid {}", id)),
+            None => Ok(()),
+        }
+    }
+
+    fn remove_track(&mut self, index: usize) -> Result<()> {
+        match self.song_id(index).map(String::from) {
+            Some(id) => self.run(&format!("deleteid {}", id)),
+            None => Ok(()),
+        }
+    }
+
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        self.uri_schemes.clone()
+    }
+
+    fn supported_mime_types(&self) -> Vec<String> {
+        self.mime_types.clone()
+    }
+
+    fn open_uri(&mut self, uri: &str) -> Result<()> {
+        let added = self.command(&format!("addid {}", quote(uri)))?;
+        match lookup(&added, "Id").map(String::from) {
+            Some(id) => self.run(&format!("playid {}", id)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Prefix turning MPD song IDs into `mpris:trackid`-style paths
+const SONG_ID_PREFIX: &str = "/mpd/song/";
+
+/// First value for `key` in a response
+fn lookup<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+/// Every value for `key` in a response (tags like Artist can repeat)
+fn lookup_all<'a>(pairs: &'a [(String, String)], key: &str) -> Vec<&'a str> {
+    pairs.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite())
+        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+}
+
+/// Song length from `duration`, falling back to the older integer `Time`
+fn song_length(song: &[(String, String)]) -> Duration {
+    lookup(song, "duration")
+        .or_else(|| lookup(song, "Time"))
+        .and_then(parse_seconds)
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Title, falling back to the stream name or file name
+fn song_title(song: &[(String, String)]) -> Option<&str> {
+    lookup(song, "Title")
+        .or_else(|| lookup(song, "Name"))
+        .or_else(|| lookup(song, "file").map(|f| f.rsplit('/').next().unwrap_or(f)))
+}
+
+/// Translate `status` and `currentsong` into a player state
+fn build_state(status: &[(String, String)], song: &[(String, String)]) -> PlayerState {
+    let flag = |key| lookup(status, key) == Some("1");
+
+    let status_value = match lookup(status, "state") {
+        Some("play") => Status::Playing,
+        Some("pause") => Status::Paused,
+        _ => Status::Stopped,
+    };
+    let loop_status = match (flag("repeat"), flag("single")) {
+        (false, _) => Loop::None,
+        (true, true) => Loop::Track,
+        (true, false) => Loop::Playlist,
+    };
+    // -1 means MPD has no mixer to control
+    let volume = lookup(status, "volume").and_then(|v| v.parse::<i32>().ok());
+    let artists = lookup_all(song, "Artist");
+
+    PlayerState {
+        connected: true,
+        player_name: "MPD".into(),
+        title: song_title(song).unwrap_or_default().to_string(),
+        artists: artists.join(", "),
+        album: lookup(song, "Album").unwrap_or_default().to_string(),
+        metadata: song_metadata(song),
+        status: status_value,
+        position: lookup(status, "elapsed")
+            .and_then(parse_seconds)
+            .unwrap_or(Duration::ZERO),
+        position_sampled_at: Some(Instant::now()),
+        rate: 1.0,
+        min_rate: 1.0,
+        max_rate: 1.0,
+        shuffle: flag("random"),
+        loop_status,
+        length: song_length(song),
+        volume: volume.map_or(0.0, |v| v.max(0) as f64 / 100.0),
+        caps: Capabilities {
+            can_control: volume.is_some_and(|v| v >= 0),
+            can_play: true,
+            can_pause: true,
+            can_seek: true,
+            can_go_next: true,
+            can_go_previous: true,
+            has_track_list: true,
+            can_edit_tracks: true,
+            ..Capabilities::default()
+        },
+        track_id: lookup(status, "songid").map(|id| format!("{}{}", SONG_ID_PREFIX, id)),
+        ..PlayerState::default()
+    }
+}
+
+/// xesam/mpris metadata for a song, so templates work as with MPRIS players
+fn song_metadata(song: &[(String, String)]) -> Metadata {
+    let mut map: HashMap<String, MetadataValue> = HashMap::new();
+    let list = |key| {
+        let values = lookup_all(song, key);
+        (!values.is_empty()).then(|| {
+            MetadataValue::Array(values.into_iter().map(|v| MetadataValue::String(v.into())).collect())
+        })
+    };
+    // Track and disc tags may be "3/12"
+    let number = |key| {
+        lookup(song, key)
+            .and_then(|v| v.split('/').next()?.trim().parse::<i32>().ok())
+            .map(MetadataValue::I32)
+    };
+
+    let fields = [
+        ("mpris:trackid", lookup(song, "Id").map(|id| MetadataValue::String(format!("{}{}", SONG_ID_PREFIX, id)))),
+        ("mpris:length", Some(MetadataValue::I64(song_length(song).as_micros() as i64))),
+        ("xesam:title", song_title(song).map(|v| MetadataValue::String(v.into()))),
+        ("xesam:artist", list("Artist")),
+        ("xesam:album", lookup(song, "Album").map(|v| MetadataValue::String(v.into()))),
+        ("xesam:albumArtist", list("AlbumArtist")),
+        ("xesam:genre", list("Genre")),
+        ("xesam:composer", list("Composer")),
+        ("xesam:trackNumber", number("Track")),
+        ("xesam:discNumber", number("Disc")),
+        ("xesam:contentCreated", lookup(song, "Date").map(|v| MetadataValue::String(v.into()))),
+        ("xesam:url", lookup(song, "file").map(|v| MetadataValue::String(v.into()))),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            map.insert(key.into(), value);
+        }
+    }
+
+    // Everything else stays reachable through `$meta[mpd:Tag]`
+    for (key, value) in song {
+        map.entry(format!("mpd:{}", key))
+            .or_insert_with(|| MetadataValue::String(value.clone()));
+    }
+
+    Metadata::from(map)
+}
+
+/// Split `playlistinfo` into tracks; each song starts with a `file` line
+fn parse_tracks(pairs: &[(String, String)]) -> Vec<Track> {
+    let mut songs: Vec<&[(String, String)]> = Vec::new();
+    let mut start = 0;
+    for (i, (key, _)) in pairs.iter().enumerate() {
+        if key == "file" && i > start {
+            songs.push(&pairs[start..i]);
+            start = i;
+        }
+    }
+    if start < pairs.len() {
+        songs.push(&pairs[start..]);
+    }
+
+    songs
+        .into_iter()
+        .filter_map(|song| {
+            Some(Track {
+                id: format!("{}{}", SONG_ID_PREFIX, lookup(song, "Id")?),
+                title: song_title(song).unwrap_or_default().to_string(),
+                artists: lookup_all(song, "Artist").join(", "),
+                album: lookup(song, "Album").unwrap_or_default().to_string(),
+                length: song_length(song),
+            })
+        })
+        .collect()
+}
+
+/// Embedded picture, falling back to a cover file in the song's directory
+fn fetch_cover(config: &MpdConfig, file: &str) -> Result<Option<Vec<u8>>> {
+    let mut conn = Connection::open(config)?;
+    match conn.read_binary("readpicture", file)? {
+        Some(data) => Ok(Some(data)),
+        None => conn.read_binary("albumart", file),
+    }
+}
+
+/// Where the cover for `file` on this server is cached
+fn cover_cache_path(config: &MpdConfig, file: &str, modified: Option<&str>) -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "mplay")?;
+    let key = [address(config).as_str(), file, modified.unwrap_or_default()].join("\0");
+    Some(proj_dirs.cache_dir().join("covers").join(format!("{:016x}", fnv1a(key.as_bytes()))))
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is the same on every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Write a cover into the cache, all at once so a half-written file is never
+/// mistaken for a cached one, then drop the oldest covers
+fn store_cover(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = path.parent().ok_or(io::ErrorKind::NotFound)?;
+    fs::create_dir_all(dir)?;
+
+    let partial = path.with_extension(format!("{}.part", std::process::id()));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, path).inspect_err(|_| {
+        let _ = fs::remove_file(&partial);
+    })?;
+
+    prune_covers(dir, MAX_CACHED_COVERS);
+    Ok(())
+}
+
+/// Remove all but the `keep` most recently written covers in `dir`
+fn prune_covers(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut covers: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if covers.len() <= keep {
+        return;
+    }
+
+    covers.sort_unstable_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in covers.drain(keep..) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Run a command the server may not know, treating an ACK as an empty response
+fn optional(conn: &mut Connection, command: &str) -> Result<Pairs> {
+    match conn.command(command) {
+        Err(err) if err.downcast_ref::<MpdError>().is_some() => Ok(Vec::new()),
+        result => result,
+    }
+}
+
+/// Quote a command argument
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Message part of `ACK [error@line] {command} message`
+fn ack_message(ack: &str) -> &str {
+    ack.split_once("} ").map_or(ack, |(_, message)| message)
+}
+
+/// Connect to the first address `host` resolves to that answers within `TIMEOUT`
+fn connect_tcp(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "host has no addresses");
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+/// Whether an error is a read or write running into `TIMEOUT`
+fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut))
+}
+
+fn address(config: &MpdConfig) -> String {
+    if config.host.starts_with('/') {
+        config.host.clone()
+    } else {
+        format!("{}:{}", config.host, config.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    type Responder = dyn Fn(&str) -> Vec<u8> + Send + Sync;
+
+    /// Scripted MPD server on a local port
+    struct FakeMpd {
+        config: MpdConfig,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeMpd {
+        /// Serve every connection, answering each command line with `respond`
+        fn start(respond: impl Fn(&str) -> Vec<u8> + Send + Sync + 'static) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+            let respond: Arc<Responder> = Arc::new(respond);
+
+            let log = Arc::clone(&commands);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let respond = Arc::clone(&respond);
+                    let log = Arc::clone(&log);
+                    thread::spawn(move || serve(stream, &*respond, &log));
+                }
+            });
+
+            Self {
+                config: MpdConfig {
+                    host: "127.0.0.1".into(),
+                    port,
+                    password: None,
+                },
+                commands,
+            }
+        }
+
+        /// Commands received on any connection, except `idle`
+        fn commands(&self) -> Vec<String> {
+            self.commands
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|c| !c.starts_with("idle"))
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn serve(stream: TcpStream, respond: &Responder, log: &Mutex<Vec<String>>) {
+        let mut writer = stream.try_clone().unwrap();
+        if writer.write_all(b"OK MPD 0.23.5\n").is_err() {
+            return;
+        }
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            log.lock().unwrap().push(line.clone());
+            if writer.write_all(&respond(&line)).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// A server playing the second of two queued songs
+    fn library(command: &str) -> Vec<u8> {
+        let response = match command {
+            "status" => concat!(
+                "volume: 40\nrepeat: 1\nrandom: 1\nsingle: 0\nplaylist: 7\n",
+                "state: play\nsong: 1\nsongid: 12\nelapsed: 61.500\nduration: 200.000\nOK\n",
+            ),
+            "currentsong" => concat!(
+                "file: music/song.flac\nArtist: A\nArtist: B\nTitle: Song\nAlbum: Album\n",
+                "Track: 3/12\nGenre: Rock\nDate: 2001-05-01\nTime: 200\nduration: 200.000\n",
+                "Pos: 1\nId: 12\nOK\n",
+            ),
+            "playlistinfo" => concat!(
+                "file: music/first.flac\nTitle: First\nArtist: A\nduration: 100.000\nPos: 0\nId: 11\n",
+                "file: music/song.flac\nTitle: Song\nArtist: A\nArtist: B\nduration: 200.000\nPos: 1\nId: 12\n",
+                "OK\n",
+            ),
+            "urlhandlers" => "handler: http://\nhandler: https://\nOK\n",
+            "decoders" => "plugin: flac\nsuffix: flac\nmime_type: audio/flac\nOK\n",
+            "password \"secret\"" => "OK\n",
+            c if c.starts_with("password") => "ACK [3@0] {password} incorrect password\n",
+            c if c.starts_with("albumart") => "ACK [50@0] {albumart} No file exists\n",
+            c if c.starts_with("idle") => {
+                // Nothing ever changes
+                thread::sleep(Duration::from_secs(3600));
+                "OK\n"
+            }
+            _ => "OK\n",
+        };
+        response.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn reads_status_and_current_song() {
+        let server = FakeMpd::start(library);
+        let mut client = MpdClient::new(server.config.clone());
+
+        let state = client.get_state();
+
+        assert!(state.connected);
+        assert_eq!(state.title, "Song");
+        assert_eq!(state.artists, "A, B");
+        assert_eq!(state.album, "Album");
+        assert_eq!(state.status, Status::Playing);
+        assert_eq!(state.position, Duration::from_millis(61_500));
+        assert_eq!(state.length, Duration::from_secs(200));
+        assert_eq!(state.volume, 0.4);
+        assert!(state.shuffle);
+        assert_eq!(state.loop_status, Loop::Playlist);
+        assert_eq!(state.track_number(), Some(3));
+        assert_eq!(state.genres(), vec!["Rock"]);
+        assert_eq!(state.meta("mpd:file").as_deref(), Some("music/song.flac"));
+
+        let titles: Vec<&str> = state.tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["First", "Song"]);
+        assert_eq!(state.track_id.as_deref(), Some(state.tracks[1].id.as_str()));
+
+        assert_eq!(client.supported_uri_schemes(), vec!["http", "https"]);
+        assert_eq!(client.supported_mime_types(), vec!["audio/flac"]);
+    }
+
+    #[test]
+    fn ignores_out_of_range_times() {
+        assert_eq!(parse_seconds("61.5"), Some(Duration::from_millis(61_500)));
+        for bad in ["-1", "inf", "NaN", "1e300"] {
+            assert_eq!(parse_seconds(bad), None, "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn controls_send_commands() {
+        let server = FakeMpd::start(library);
+        let mut client = MpdClient::new(server.config.clone());
+        client.get_state();
+
+        client.toggle().unwrap();
+        client.seek_forward(Duration::from_secs(5)).unwrap();
+        client.adjust_volume(0.05).unwrap();
+        client.cycle_loop().unwrap();
+        client.go_to(0).unwrap();
+        client.remove_track(1).unwrap();
+
+        let commands = server.commands();
+        let sent: Vec<&str> = commands
+            .iter()
+            .map(String::as_str)
+            .skip_while(|c| *c != "playlistinfo")
+            .skip(1)
+            // The cover is fetched on its own connection
+            .filter(|c| !c.starts_with("readpicture") && !c.starts_with("albumart"))
+            .collect();
+        assert_eq!(
+            sent,
+            vec![
+                "pause 1",
+                "seekcur +5.000",
+                "setvol 45",
+                "repeat 0",
+                "single 0",
+                "playid 11",
+                "deleteid 12",
+            ]
+        );
+    }
+
+    #[test]
+    fn rate_is_unsupported() {
+        let server = FakeMpd::start(library);
+        let mut client = MpdClient::new(server.config.clone());
+        client.get_state();
+
+        let err = client.adjust_rate(0.1).unwrap_err();
+        assert_eq!(err.to_string(), "Playback rate not supported by MPD");
+    }
+
+    #[test]
+    fn authenticates_with_password() {
+        let server = FakeMpd::start(library);
+
+        let mut config = server.config.clone();
+        config.password = Some("secret".into());
+        assert!(MpdClient::new(config.clone()).connect().unwrap());
+        assert!(server.commands().contains(&"password \"secret\"".to_string()));
+
+        config.password = Some("wrong".into());
+        let err = MpdClient::new(config).connect().unwrap_err();
+        assert_eq!(err.to_string(), "MPD: incorrect password");
+    }
+
+    #[test]
+    fn reassembles_chunked_cover_art() {
+        let cover: Vec<u8> = (0..10).collect();
+        let image = cover.clone();
+        let server = FakeMpd::start(move |command| {
+            let Some(args) = command.strip_prefix("albumart ") else {
+                return b"OK\n".to_vec();
+            };
+            let offset: usize = args.rsplit(' ').next().unwrap().parse().unwrap();
+            let chunk = &image[offset..(offset + 4).min(image.len())];
+
+            let mut response = format!("size: {}\nbinary: {}\n", image.len(), chunk.len()).into_bytes();
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\nOK\n");
+            response
+        });
+
+        let fetched = fetch_cover(&server.config, "music/song.flac").unwrap();
+
+        assert_eq!(fetched, Some(cover));
+        let commands = server.commands();
+        assert_eq!(commands[0], "readpicture \"music/song.flac\" 0");
+        assert_eq!(commands[1..], [
+            "albumart \"music/song.flac\" 0",
+            "albumart \"music/song.flac\" 4",
+            "albumart \"music/song.flac\" 8",
+        ]);
+    }
+
+    #[test]
+    fn rejects_oversized_cover_art() {
+        let server = FakeMpd::start(|command| {
+            if command.starts_with("readpicture") {
+                // Announces 4 bytes, then claims a huge chunk
+                return b"size: 4\nbinary: 4000000000\n".to_vec();
+            }
+            library(command)
+        });
+
+        let err = fetch_cover(&server.config, "music/song.flac").unwrap_err();
+        assert_eq!(err.to_string(), "MPD sent more picture data than announced");
+    }
+
+    #[test]
+    fn cover_cache_keys_are_stable_and_per_server() {
+        // Published FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let config = MpdConfig::default();
+        let mut other = config.clone();
+        other.port += 1;
+        let path = |config, modified| cover_cache_path(config, "music/song.flac", modified);
+        assert_ne!(path(&config, None), path(&other, None));
+        assert_ne!(path(&config, Some("2024-01-01T00:00:00Z")), path(&config, None));
+        assert_eq!(path(&config, None), path(&config.clone(), None));
+    }
+
+    #[test]
+    fn stores_covers_atomically_and_prunes_old_ones() {
+        let dir = std::env::temp_dir().join(format!("mplay-covers-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for name in ["a", "b", "c"] {
+            store_cover(&dir.join(name), name.as_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+        prune_covers(&dir, 2);
+
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["b", "c"]);
+        assert_eq!(fs::read(dir.join("c")).unwrap(), b"c");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn hung_server_times_out() {
+        let server = FakeMpd::start(|command| {
+            if command == "status" {
+                thread::sleep(Duration::from_secs(60));
+            }
+            library(command)
+        });
+        let mut client = MpdClient::new(server.config.clone());
+
+        let started = Instant::now();
+        assert!(!client.get_state().connected);
+        assert!(started.elapsed() < TIMEOUT + Duration::from_secs(1));
+
+        // Polling again right away doesn't wait out another timeout
+        let started = Instant::now();
+        assert!(!client.get_state().connected);
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn connecting_tolerates_only_acks() {
+        let server = FakeMpd::start(|command| match command {
+            "urlhandlers" => b"ACK [5@0] {} unknown command \"urlhandlers\"\n".to_vec(),
+            _ => library(command),
+        });
+        let mut client = MpdClient::new(server.config.clone());
+        assert!(client.connect().unwrap());
+        assert!(client.supported_uri_schemes().is_empty());
+        assert_eq!(client.supported_mime_types(), vec!["audio/flac"]);
+
+        let server = FakeMpd::start(|command| {
+            if command == "decoders" {
+                thread::sleep(TIMEOUT + Duration::from_secs(1));
+            }
+            library(command)
+        });
+        let mut client = MpdClient::new(server.config.clone());
+        assert!(client.connect().is_err());
+        assert!(!client.get_state().connected);
+    }
+
+    #[test]
+    fn idle_changes_refresh_state() {
+        let idles = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&idles);
+        let server = FakeMpd::start(move |command| {
+            if command.starts_with("idle") && counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                return b"changed: player\nOK\n".to_vec();
+            }
+            library(command)
+        });
+        let mut client = MpdClient::new(server.config.clone());
+        client.connect().unwrap();
+        assert!(client.emits_signals());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !client.process_events() {
+            assert!(Instant::now() < deadline, "no idle notification");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(client.state().title, "Song");
+    }
+}
@@ -1,22 +1,214 @@
 use anyhow::{Context, Result};
-use mpris::{Metadata, PlaybackStatus, Player, PlayerFinder};
-use std::time::Duration;
+use crate::backend::{unsupported, PlayerBackend};
+use crate::config::{FollowMode, PlaylistOrder};
+use crate::log;
+use crate::mpris_server::{self, MprisServer};
+use dbus::blocking::{Connection, Proxy};
+use dbus::channel::MatchingReceiver;
+use dbus::message::MatchRule;
+use mpris::{LoopStatus, Metadata, MetadataValue, PlaybackStatus, Player, PlayerFinder, TrackID};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub(crate) const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+pub(crate) const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+pub(crate) const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const TRACKLIST_INTERFACE: &str = "org.mpris.MediaPlayer2.TrackList";
+const PLAYLISTS_INTERFACE: &str = "org.mpris.MediaPlayer2.Playlists";
+
+/// Timeout for calls the mpris crate doesn't wrap
+pub(crate) const DBUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Playback rate change per rate up/down action
+pub const RATE_STEP: f64 = 0.1;
 
 /// Current player state
 #[derive(Debug, Clone, Default)]
 pub struct PlayerState {
     pub connected: bool,
+    /// The player's `Identity`
     pub player_name: String,
+    /// Basename of the player's `.desktop` file, if it has one
+    pub desktop_entry: String,
+    pub fullscreen: bool,
     pub title: String,
     pub artists: String,
     pub album: String,
     pub art_url: Option<String>,
+    /// Everything the player reported for the current track
+    pub metadata: Metadata,
     pub status: Status,
+    /// Position at the time of the last sample; use `current_position` for display
     pub position: Duration,
+    /// When `position` was sampled
+    pub position_sampled_at: Option<Instant>,
+    /// Playback rate (1.0 is normal speed)
+    pub rate: f64,
+    pub min_rate: f64,
+    pub max_rate: f64,
+    pub shuffle: bool,
+    pub loop_status: Loop,
     pub length: Duration,
     pub volume: f64,
+    pub caps: Capabilities,
+    /// `mpris:trackid` of the current track
+    pub track_id: Option<String>,
+    /// Contents of the player's TrackList, if it has one
+    pub tracks: Vec<Track>,
+    /// Playlist the player is currently playing from
+    pub active_playlist: Option<Playlist>,
+}
+
+/// Entry in the player's TrackList
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    pub id: String,
+    pub title: String,
+    pub artists: String,
+    pub album: String,
+    pub length: Duration,
+}
+
+impl Track {
+    fn from_metadata(metadata: Metadata) -> Option<Self> {
+        let id = metadata.track_id()?.as_str().to_string();
+        let metadata = Some(metadata);
+        Some(Self {
+            id,
+            title: extract_title(&metadata),
+            artists: extract_artists(&metadata),
+            album: extract_album(&metadata),
+            length: metadata
+                .as_ref()
+                .and_then(|m| m.length())
+                .unwrap_or(Duration::ZERO),
+        })
+    }
+}
+
+/// Playlist as exposed by the Playlists interface: `(oss)`
+type RawPlaylist = (dbus::Path<'static>, String, String);
+
+/// Entry from the player's Playlists interface
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Playlist {
+    /// Object path identifying the playlist
+    pub id: String,
+    pub name: String,
+    /// Icon URI, if the player provides one
+    pub icon: Option<String>,
+}
+
+impl Playlist {
+    fn from_raw((id, name, icon): RawPlaylist) -> Self {
+        Self {
+            id: id.to_string(),
+            name,
+            icon: (!icon.is_empty()).then_some(icon),
+        }
+    }
+}
+
+/// What the connected player allows us to do
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Capabilities {
+    pub can_control: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_seek: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub has_track_list: bool,
+    pub can_edit_tracks: bool,
+    pub has_playlists: bool,
+    pub can_raise: bool,
+    pub can_quit: bool,
+    pub can_set_fullscreen: bool,
+}
+
+impl Capabilities {
+    fn from_player(player: &Player) -> Self {
+        // Treat unreadable properties as supported rather than locking the user out
+        let has_track_list =
+            player.supports_track_lists() && player.get_has_track_list().unwrap_or(true);
+        let mut caps = Self {
+            can_control: player.can_control().unwrap_or(true),
+            can_play: player.can_play().unwrap_or(true),
+            can_pause: player.can_pause().unwrap_or(true),
+            can_seek: player.can_seek().unwrap_or(true),
+            can_go_next: player.can_go_next().unwrap_or(true),
+            can_go_previous: player.can_go_previous().unwrap_or(true),
+            has_track_list,
+            can_edit_tracks: has_track_list && player.can_edit_tracks().unwrap_or(false),
+            // Not covered by the mpris crate; probed separately
+            has_playlists: false,
+            can_raise: player.can_raise().unwrap_or(true),
+            can_quit: player.can_quit().unwrap_or(true),
+            can_set_fullscreen: player.can_set_fullscreen().unwrap_or(false),
+        };
+        caps.normalize();
+        caps
+    }
+
+    /// Update a capability from its MPRIS property name
+    fn set(&mut self, name: &str, value: bool) {
+        match name {
+            "CanControl" => self.can_control = value,
+            "CanPlay" => self.can_play = value,
+            "CanPause" => self.can_pause = value,
+            "CanSeek" => self.can_seek = value,
+            "CanGoNext" => self.can_go_next = value,
+            "CanGoPrevious" => self.can_go_previous = value,
+            "CanRaise" => self.can_raise = value,
+            "CanQuit" => self.can_quit = value,
+            "CanSetFullscreen" => self.can_set_fullscreen = value,
+            "HasTrackList" => {
+                self.has_track_list = value;
+                self.can_edit_tracks &= value;
+            }
+            "CanEditTracks" => self.can_edit_tracks = value && self.has_track_list,
+            _ => return,
+        }
+        self.normalize();
+    }
+
+    /// Per the spec, a player that can't be controlled can't do anything else
+    /// either, except through the root interface
+    fn normalize(&mut self) {
+        if !self.can_control {
+            *self = Self {
+                has_track_list: self.has_track_list,
+                has_playlists: self.has_playlists,
+                can_raise: self.can_raise,
+                can_quit: self.can_quit,
+                can_set_fullscreen: self.can_set_fullscreen,
+                ..Self::default()
+            };
+        }
+    }
+}
+
+/// Error for actions the connected player doesn't support
+#[derive(Debug)]
+pub struct Unsupported {
+    pub action: &'static str,
+    pub player: String,
 }
 
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} not supported by {}", self.action, self.player)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
 /// Playback status
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum Status {
@@ -37,6 +229,15 @@ impl From<PlaybackStatus> for Status {
 }
 
 impl Status {
+    fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "Playing" => Some(Status::Playing),
+            "Paused" => Some(Status::Paused),
+            "Stopped" => Some(Status::Stopped),
+            _ => None,
+        }
+    }
+
     pub fn icon(&self) -> &'static str {
         match self {
             Status::Playing => "⏸",
@@ -46,55 +247,524 @@ impl Status {
     }
 }
 
+/// Loop status
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Loop {
+    #[default]
+    None,
+    Track,
+    Playlist,
+}
+
+impl From<LoopStatus> for Loop {
+    fn from(s: LoopStatus) -> Self {
+        match s {
+            LoopStatus::None => Loop::None,
+            LoopStatus::Track => Loop::Track,
+            LoopStatus::Playlist => Loop::Playlist,
+        }
+    }
+}
+
+impl From<Loop> for LoopStatus {
+    fn from(l: Loop) -> Self {
+        match l {
+            Loop::None => LoopStatus::None,
+            Loop::Track => LoopStatus::Track,
+            Loop::Playlist => LoopStatus::Playlist,
+        }
+    }
+}
+
+impl Loop {
+    fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "None" => Some(Loop::None),
+            "Track" => Some(Loop::Track),
+            "Playlist" => Some(Loop::Playlist),
+            _ => None,
+        }
+    }
+
+    /// Next mode in the None → Track → Playlist cycle
+    pub fn next(self) -> Self {
+        match self {
+            Loop::None => Loop::Track,
+            Loop::Track => Loop::Playlist,
+            Loop::Playlist => Loop::None,
+        }
+    }
+}
+
+/// Summary of an available player, as listed in the player picker
+#[derive(Debug, Clone)]
+pub struct PlayerSummary {
+    pub bus_name: String,
+    pub unique_name: String,
+    pub identity: String,
+    pub status: Status,
+    pub title: String,
+}
+
+impl PlayerSummary {
+    fn from_player(player: &Player) -> Self {
+        let metadata = player.get_metadata().ok();
+        Self {
+            bus_name: player.bus_name().to_string(),
+            unique_name: player.unique_name().to_string(),
+            identity: player.identity().to_string(),
+            status: player
+                .get_playback_status()
+                .map(Status::from)
+                .unwrap_or_default(),
+            title: extract_title(&metadata),
+        }
+    }
+
+    /// How well this player matches a user-supplied name, lower is better:
+    /// the exact bus name, then an exact identity or bus name suffix
+    /// (`vlc` for `org.mpris.MediaPlayer2.vlc`), then a prefix, then a substring
+    fn match_rank(&self, name: &str) -> Option<u8> {
+        if self.bus_name == name {
+            return Some(0);
+        }
+
+        let name = name.to_lowercase();
+        let identity = self.identity.to_lowercase();
+        let bus_name = self.bus_name.to_lowercase();
+        let short_name = bus_name
+            .strip_prefix(&format!("{}.", ROOT_INTERFACE.to_lowercase()))
+            .unwrap_or(&bus_name);
+
+        if identity == name || short_name == name || bus_name == name {
+            Some(1)
+        } else if identity.starts_with(&name) || short_name.starts_with(&name) {
+            Some(2)
+        } else if identity.contains(&name) || bus_name.contains(&name) {
+            Some(3)
+        } else {
+            None
+        }
+    }
+}
+
+/// The player that best matches a user-supplied name; the first on ties
+fn best_match<'a>(players: &'a [PlayerSummary], name: &str) -> Option<&'a PlayerSummary> {
+    players
+        .iter()
+        .enumerate()
+        .filter_map(|(index, p)| Some((p.match_rank(name)?, index, p)))
+        .min_by_key(|(rank, index, _)| (*rank, *index))
+        .map(|(_, _, p)| p)
+}
+
+/// Change notification received from the player's D-Bus signals
+#[derive(Debug)]
+pub(crate) enum PlayerEvent {
+    /// `PropertiesChanged` on the root or player interface
+    PropertiesChanged {
+        changed: HashMap<String, MetadataValue>,
+        invalidated: Vec<String>,
+    },
+    /// `Seeked` with the new position in microseconds
+    Seeked(i64),
+    /// `TrackListReplaced` with the new track IDs
+    TrackListReplaced(Vec<String>),
+    /// `TrackAdded` after the given track ID
+    TrackAdded {
+        metadata: HashMap<String, MetadataValue>,
+        after: String,
+    },
+    /// `TrackRemoved`
+    TrackRemoved(String),
+    /// `TrackMetadataChanged` for the given (old) track ID
+    TrackMetadataChanged {
+        id: String,
+        metadata: HashMap<String, MetadataValue>,
+    },
+    /// TrackList properties changed; the list must be re-read
+    TrackListChanged,
+    /// Playlists properties changed; the active playlist must be re-read
+    PlaylistsChanged,
+    /// `PlaylistChanged` with the playlist's new name or icon
+    PlaylistChanged(Playlist),
+    /// The player's bus name lost its owner
+    Quit,
+}
+
+/// Background thread receiving D-Bus signals on its own session connection.
+///
+/// The thread stops when this handle is dropped.
+struct SignalThread<T> {
+    receiver: Receiver<T>,
+    stop: Arc<AtomicBool>,
+    /// Set once the channel disconnects because the thread ended
+    finished: Cell<bool>,
+}
+
+impl<T: Send + 'static> SignalThread<T> {
+    fn spawn<F>(run: F) -> Self
+    where
+        F: FnOnce(Sender<T>, &AtomicBool) -> Result<()> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+
+        // Ending for any reason, even a panic, drops `tx`, which `drain` notices
+        thread::spawn(move || {
+            if let Err(err) = run(tx, &stop_flag) {
+                log::error(&err);
+            }
+        });
+
+        Self {
+            receiver: rx,
+            stop,
+            finished: Cell::new(false),
+        }
+    }
+
+    /// Drain everything received so far
+    fn drain(&self) -> Vec<T> {
+        let mut received = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(item) => received.push(item),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished.set(true);
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// Whether the thread has stopped delivering
+    fn finished(&self) -> bool {
+        self.finished.get()
+    }
+}
+
+impl<T> Drop for SignalThread<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Dispatch signals until asked to stop
+fn process_until_stopped(conn: &Connection, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        conn.process(Duration::from_millis(250))
+            .context("Failed to process D-Bus messages")?;
+    }
+    Ok(())
+}
+
+/// Signal loop for the connected player
+fn listen_player(
+    bus_name: &str,
+    unique_name: &str,
+    tx: Sender<PlayerEvent>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let conn = Connection::new_session()
+        .context("Failed to open D-Bus session connection")?;
+
+    let rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+        .with_sender(unique_name.to_string())
+        .with_path(MPRIS_PATH);
+    let sender = tx.clone();
+    conn.add_match(
+        rule,
+        move |(interface, changed, invalidated): (String, HashMap<String, MetadataValue>, Vec<String>), _, _| {
+            match interface.as_str() {
+                ROOT_INTERFACE | PLAYER_INTERFACE => sender
+                    .send(PlayerEvent::PropertiesChanged { changed, invalidated })
+                    .is_ok(),
+                TRACKLIST_INTERFACE => {
+                    // CanEditTracks is a capability; anything else is the Tracks list
+                    let caps: HashMap<_, _> =
+                        changed.into_iter().filter(|(name, _)| name == "CanEditTracks").collect();
+                    let caps_sent = caps.is_empty()
+                        || sender
+                            .send(PlayerEvent::PropertiesChanged { changed: caps, invalidated: Vec::new() })
+                            .is_ok();
+                    caps_sent && sender.send(PlayerEvent::TrackListChanged).is_ok()
+                }
+                // ActivePlaylist is a struct, which only decodes as unsupported
+                PLAYLISTS_INTERFACE => sender.send(PlayerEvent::PlaylistsChanged).is_ok(),
+                _ => true,
+            }
+        },
+    )
+    .context("Failed to subscribe to PropertiesChanged")?;
+
+    let rule = MatchRule::new_signal(PLAYLISTS_INTERFACE, "PlaylistChanged")
+        .with_sender(unique_name.to_string())
+        .with_path(MPRIS_PATH);
+    let sender = tx.clone();
+    conn.add_match(rule, move |(playlist,): (RawPlaylist,), _, _| {
+        sender.send(PlayerEvent::PlaylistChanged(Playlist::from_raw(playlist))).is_ok()
+    })
+    .context("Failed to subscribe to PlaylistChanged")?;
+
+    let rule = MatchRule::new_signal(PLAYER_INTERFACE, "Seeked")
+        .with_sender(unique_name.to_string())
+        .with_path(MPRIS_PATH);
+    let sender = tx.clone();
+    conn.add_match(rule, move |(position,): (i64,), _, _| {
+        sender.send(PlayerEvent::Seeked(position)).is_ok()
+    })
+    .context("Failed to subscribe to Seeked")?;
+
+    // TrackList signals carry object paths, so parse them by hand
+    let rule = MatchRule::new()
+        .with_type(dbus::MessageType::Signal)
+        .with_sender(unique_name.to_string())
+        .with_path(MPRIS_PATH)
+        .with_interface(TRACKLIST_INTERFACE);
+    conn.add_match_no_cb(&rule.match_str())
+        .context("Failed to subscribe to TrackList signals")?;
+    let sender = tx.clone();
+    conn.start_receive(
+        rule.static_clone(),
+        Box::new(move |msg, _| match parse_track_list_signal(&msg) {
+            Some(event) => sender.send(event).is_ok(),
+            None => true,
+        }),
+    );
+
+    let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+        .with_sender("org.freedesktop.DBus");
+    let watched = bus_name.to_string();
+    conn.add_match(rule, move |(name, _old, new): (String, String, String), _, _| {
+        if name == watched && new.is_empty() {
+            let _ = tx.send(PlayerEvent::Quit);
+            return false;
+        }
+        true
+    })
+    .context("Failed to subscribe to NameOwnerChanged")?;
+
+    process_until_stopped(&conn, stop)
+}
+
+/// Translate a TrackList interface signal into an event
+fn parse_track_list_signal(msg: &dbus::Message) -> Option<PlayerEvent> {
+    use dbus::Path;
+
+    match msg.member()?.as_ref() {
+        "TrackListReplaced" => {
+            let (tracks, _current): (Vec<Path>, Path) = msg.read2().ok()?;
+            Some(PlayerEvent::TrackListReplaced(
+                tracks.iter().map(|p| p.to_string()).collect(),
+            ))
+        }
+        "TrackAdded" => {
+            let (metadata, after): (HashMap<String, MetadataValue>, Path) = msg.read2().ok()?;
+            Some(PlayerEvent::TrackAdded {
+                metadata,
+                after: after.to_string(),
+            })
+        }
+        "TrackRemoved" => {
+            let id: Path = msg.read1().ok()?;
+            Some(PlayerEvent::TrackRemoved(id.to_string()))
+        }
+        "TrackMetadataChanged" => {
+            let (id, metadata): (Path, HashMap<String, MetadataValue>) = msg.read2().ok()?;
+            Some(PlayerEvent::TrackMetadataChanged {
+                id: id.to_string(),
+                metadata,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Signal loop reporting the unique name of every player that starts playing
+fn listen_activity(tx: Sender<String>, stop: &AtomicBool) -> Result<()> {
+    let conn = Connection::new_session()
+        .context("Failed to open D-Bus session connection")?;
+
+    let rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+        .with_path(MPRIS_PATH);
+    conn.add_match(
+        rule,
+        move |(interface, changed, _): (String, HashMap<String, MetadataValue>, Vec<String>), _, msg| {
+            let playing = interface == PLAYER_INTERFACE
+                && changed.get("PlaybackStatus").and_then(|v| v.as_str()) == Some("Playing");
+            match msg.sender() {
+                Some(sender) if playing => tx.send(sender.to_string()).is_ok(),
+                _ => true,
+            }
+        },
+    )
+    .context("Failed to subscribe to PropertiesChanged")?;
+
+    process_until_stopped(&conn, stop)
+}
+
+/// Signal loop reporting the bus name of every MPRIS player that appears
+fn listen_arrivals(tx: Sender<String>, stop: &AtomicBool) -> Result<()> {
+    let conn = Connection::new_session()
+        .context("Failed to open D-Bus session connection")?;
+
+    let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+        .with_sender("org.freedesktop.DBus");
+    conn.add_match(rule, move |(name, old, new): (String, String, String), _, _| {
+        let is_player = name
+            .strip_prefix(ROOT_INTERFACE)
+            .is_some_and(|rest| rest.starts_with('.'));
+        if is_player && old.is_empty() && !new.is_empty() {
+            return tx.send(name).is_ok();
+        }
+        true
+    })
+    .context("Failed to subscribe to NameOwnerChanged")?;
+
+    process_until_stopped(&conn, stop)
+}
+
 /// MPRIS client for controlling media players
 pub struct MprisClient {
     player: Option<Player>,
     preferred_players: Vec<String>,
+    follow_mode: FollowMode,
+    /// Temporarily keep the current player regardless of `follow_mode`
+    pinned: bool,
+    state: PlayerState,
+    listener: Option<SignalThread<PlayerEvent>>,
+    /// Set once the connected player has emitted at least one signal
+    signals_seen: bool,
+    /// Bus-wide watcher for players entering `Playing`
+    activity: Option<SignalThread<String>>,
+    /// Bus-wide watcher for players starting, to move up `preferred_players`
+    arrivals: Option<SignalThread<String>>,
+    /// Every player found on the bus at the last refresh
+    players: Vec<PlayerSummary>,
+    /// Session connection for interfaces the mpris crate doesn't wrap
+    bus: Option<Connection>,
+    /// The player's playlists as of the last `refresh_playlists`
+    playlists: Vec<Playlist>,
+    /// Our own MPRIS name, mirroring the connected player
+    server: Option<MprisServer>,
 }
 
 impl MprisClient {
-    /// Create a new MPRIS client
-    pub fn new(preferred_players: Vec<String>) -> Self {
+    /// Create a new MPRIS client, optionally mirroring the connected player
+    /// under `org.mpris.MediaPlayer2.mplay`
+    pub fn new(preferred_players: Vec<String>, follow_mode: FollowMode, serve: bool) -> Self {
+        let activity = (follow_mode == FollowMode::MostRecentActive)
+            .then(|| SignalThread::spawn(listen_activity));
+        let arrivals = (follow_mode == FollowMode::Preferred && !preferred_players.is_empty())
+            .then(|| SignalThread::spawn(listen_arrivals));
+
+        // Without the server the client still works, so only log why it failed
+        let server = if serve {
+            MprisServer::new().map_err(|e| log::error(&e)).ok()
+        } else {
+            None
+        };
+
         Self {
             player: None,
             preferred_players,
+            follow_mode,
+            pinned: false,
+            state: PlayerState::default(),
+            listener: None,
+            signals_seen: false,
+            activity,
+            arrivals,
+            players: Vec::new(),
+            bus: Connection::new_session().ok(),
+            playlists: Vec::new(),
+            server,
         }
     }
 
-    /// Try to connect to a media player
-    pub fn connect(&mut self) -> Result<bool> {
-        let finder = PlayerFinder::new()
-            .context("Failed to create player finder")?;
+    /// Every player on the bus except mirrors like our own, which would
+    /// otherwise end up following themselves
+    fn find_players(finder: &PlayerFinder) -> Vec<Player> {
+        finder
+            .find_all()
+            .map(|players| {
+                players
+                    .into_iter()
+                    .filter(|p| p.bus_name() != mpris_server::BUS_NAME)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
-        // Try preferred players first
-        for preferred in &self.preferred_players {
-            let preferred_lower = preferred.to_lowercase();
-            if let Ok(players) = finder.find_all() {
-                for player in players {
-                    let identity = player.identity().to_lowercase();
-                    if identity.contains(&preferred_lower) {
-                        self.player = Some(player);
-                        return Ok(true);
-                    }
+    /// Pick the player `PlayerFinder::find_active` would: the first playing,
+    /// else the first paused, else the first with a track, else the first
+    fn find_active(players: Vec<Player>) -> Option<Player> {
+        let mut paused = None;
+        let mut with_track = None;
+        let mut first = None;
+
+        for player in players {
+            match player.get_playback_status() {
+                Ok(PlaybackStatus::Playing) => return Some(player),
+                Ok(PlaybackStatus::Paused) if paused.is_none() => paused = Some(player),
+                _ if with_track.is_none()
+                    && player.get_metadata().is_ok_and(|m| !m.is_empty()) =>
+                {
+                    with_track = Some(player)
                 }
+                _ if first.is_none() => first = Some(player),
+                _ => {}
             }
         }
 
-        // Fall back to any active player
-        if let Ok(player) = finder.find_active() {
-            self.player = Some(player);
+        paused.or(with_track).or(first)
+    }
+
+    /// Point the mirror at the connected player and answer pending calls
+    fn update_server(&mut self) {
+        if let Some(ref mut server) = self.server {
+            server.follow(self.player.as_ref().map(|p| p.unique_name()));
+            server.process();
+        }
+    }
+
+    /// Connect to the player owning `bus_name`
+    fn switch_to(&mut self, bus_name: &str) -> Result<bool> {
+        if self.active_bus_name() == Some(bus_name) {
             return Ok(true);
         }
 
-        // Try first available player
-        if let Ok(players) = finder.find_all() {
-            if let Some(player) = players.into_iter().next() {
-                self.player = Some(player);
-                return Ok(true);
+        let finder = PlayerFinder::new()
+            .context("Failed to create player finder")?;
+
+        let player = Self::find_players(&finder)
+            .into_iter()
+            .find(|p| p.bus_name() == bus_name);
+
+        match player {
+            Some(player) => {
+                self.set_player(player);
+                self.get_state();
+                Ok(true)
             }
+            None => Ok(false),
         }
+    }
 
-        Ok(false)
+    /// Switch to a player and start listening for its signals
+    fn set_player(&mut self, player: Player) {
+        let bus_name = player.bus_name().to_string();
+        let unique_name = player.unique_name().to_string();
+        self.listener = Some(SignalThread::spawn(move |tx, stop| {
+            listen_player(&bus_name, &unique_name, tx, stop)
+        }));
+        self.signals_seen = false;
+        self.playlists.clear();
+        self.player = Some(player);
     }
 
     /// Check if still connected and reconnect if needed
@@ -108,18 +778,402 @@ impl MprisClient {
 
         // Try to reconnect
         self.player = None;
+        self.listener = None;
         self.connect().unwrap_or(false)
     }
 
+    /// Switch to the player that most recently started playing.
+    ///
+    /// Returns true if the active player changed.
+    fn follow_activity(&mut self) -> bool {
+        let latest = match self.activity {
+            Some(ref activity) => activity.drain().pop(),
+            None => None,
+        };
+
+        let unique_name = match latest {
+            Some(name) if !self.is_pinned() => name,
+            _ => return false,
+        };
+
+        if self.player.as_ref().map(|p| p.unique_name()) == Some(unique_name.as_str()) {
+            return false;
+        }
+
+        let bus_name = self.refresh_players().ok().and_then(|players| {
+            players
+                .iter()
+                .find(|p| p.unique_name == unique_name)
+                .map(|p| p.bus_name.clone())
+        });
+
+        match bus_name {
+            Some(bus_name) => self.switch_to(&bus_name).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Switch to a newly started player listed earlier in `preferred_players`
+    /// than the connected one.
+    ///
+    /// Returns true if the active player changed.
+    fn follow_preferred(&mut self) -> bool {
+        let arrived = match self.arrivals {
+            Some(ref arrivals) => arrivals.drain(),
+            None => return false,
+        };
+        if arrived.is_empty() || self.is_pinned() || self.refresh_players().is_err() {
+            return false;
+        }
+
+        let current = self
+            .player
+            .as_ref()
+            .and_then(|p| self.preferred_rank(p.identity()))
+            .unwrap_or(usize::MAX);
+        let best = self
+            .players
+            .iter()
+            .filter(|p| arrived.contains(&p.bus_name))
+            .filter_map(|p| Some((self.preferred_rank(&p.identity)?, p.bus_name.clone())))
+            .filter(|(rank, _)| *rank < current)
+            .min();
+
+        match best {
+            Some((_, bus_name)) => self.switch_to(&bus_name).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Position of the first `preferred_players` entry an identity matches
+    fn preferred_rank(&self, identity: &str) -> Option<usize> {
+        let identity = identity.to_lowercase();
+        self.preferred_players
+            .iter()
+            .position(|preferred| identity.contains(&preferred.to_lowercase()))
+    }
+
+    /// Re-read every capability but the separately probed playlist support
+    fn refresh_caps(&mut self) {
+        if let Some(ref player) = self.player {
+            self.state.caps = Capabilities {
+                has_playlists: self.state.caps.has_playlists,
+                ..Capabilities::from_player(player)
+            };
+        }
+    }
+
+    /// Re-read the TrackList, fetching metadata only if the track IDs changed
+    pub fn refresh_tracks(&mut self) {
+        let ids: Vec<String> = match self.player {
+            Some(ref player) if self.state.caps.has_track_list => player
+                .get_track_list()
+                .map(|list| list.ids().iter().map(|id| id.as_str().to_string()).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        let unchanged = ids.len() == self.state.tracks.len()
+            && ids.iter().zip(&self.state.tracks).all(|(id, t)| *id == t.id);
+        if !unchanged {
+            self.load_tracks(&ids);
+        }
+    }
+
+    /// Replace the cached TrackList with metadata for `ids`
+    fn load_tracks(&mut self, ids: &[String]) {
+        let Some(ref player) = self.player else {
+            return;
+        };
+
+        let track_ids: Vec<TrackID> = ids
+            .iter()
+            .filter_map(|id| TrackID::new(id.as_str()).ok())
+            .collect();
+
+        self.state.tracks = if track_ids.is_empty() {
+            Vec::new()
+        } else {
+            player
+                .get_tracks_metadata(&track_ids)
+                .map(|all| all.into_iter().filter_map(Track::from_metadata).collect())
+                .unwrap_or_default()
+        };
+    }
+
+    /// Proxy for the connected player's MPRIS object
+    fn proxy(&self) -> Option<Proxy<'_, &Connection>> {
+        let bus = self.bus.as_ref()?;
+        let player = self.player.as_ref()?;
+        Some(bus.with_proxy(player.unique_name(), MPRIS_PATH, DBUS_TIMEOUT))
+    }
+
+    /// Whether the connected player implements the Playlists interface
+    fn supports_playlists(&self) -> bool {
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+
+        self.proxy()
+            .is_some_and(|proxy| proxy.get::<u32>(PLAYLISTS_INTERFACE, "PlaylistCount").is_ok())
+    }
+
+    /// Read `ActivePlaylist`, which is only meaningful when its valid flag is set
+    fn read_active_playlist(&self) -> Option<Playlist> {
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+
+        let (valid, playlist): (bool, RawPlaylist) = self
+            .proxy()?
+            .get(PLAYLISTS_INTERFACE, "ActivePlaylist")
+            .ok()?;
+        valid.then(|| Playlist::from_raw(playlist))
+    }
+
+    /// Fail with `Unsupported` unless the connected player allows an action
+    fn require(&self, allowed: bool, action: &'static str) -> Result<()> {
+        if self.player.is_some() && !allowed {
+            return Err(unsupported(&self.state, action));
+        }
+        Ok(())
+    }
+}
+
+impl PlayerBackend for MprisClient {
+    /// Last known player state
+    fn state(&self) -> &PlayerState {
+        &self.state
+    }
+
+    /// Whether the connected player delivers updates through signals.
+    ///
+    /// Until the first signal arrives, callers should keep polling with `get_state`.
+    fn emits_signals(&self) -> bool {
+        self.listener.as_ref().is_some_and(|listener| !listener.finished()) && self.signals_seen
+    }
+
+    /// Whether automatic player switching is currently suspended
+    fn is_pinned(&self) -> bool {
+        self.pinned || self.follow_mode == FollowMode::Pinned
+    }
+
+    /// Lock or unlock the current player against automatic switching
+    fn toggle_pin(&mut self) -> bool {
+        self.pinned = !self.pinned;
+        self.pinned
+    }
+
+    /// Players found at the last `refresh_players`
+    fn players(&self) -> &[PlayerSummary] {
+        &self.players
+    }
+
+    /// Bus name of the connected player
+    fn active_bus_name(&self) -> Option<&str> {
+        self.player.as_ref().map(|p| p.bus_name())
+    }
+
+    /// Re-scan the bus for available players
+    fn refresh_players(&mut self) -> Result<&[PlayerSummary]> {
+        let finder = PlayerFinder::new()
+            .context("Failed to create player finder")?;
+
+        self.players = Self::find_players(&finder)
+            .iter()
+            .map(PlayerSummary::from_player)
+            .collect();
+
+        Ok(&self.players)
+    }
+
+    /// Switch to the first player whose bus name or identity matches `name`
+    fn select_player(&mut self, name: &str) -> Result<bool> {
+        self.refresh_players()?;
+
+        let bus_name = best_match(&self.players, name).map(|p| p.bus_name.clone());
+
+        match bus_name {
+            Some(bus_name) => self.switch_to(&bus_name),
+            None => Ok(false),
+        }
+    }
+
+    /// Switch to the next (or previous) player on the bus
+    fn cycle_player(&mut self, forward: bool) -> Result<bool> {
+        self.refresh_players()?;
+
+        if self.players.is_empty() {
+            return Ok(false);
+        }
+
+        let len = self.players.len();
+        let current = self
+            .active_bus_name()
+            .and_then(|name| self.players.iter().position(|p| p.bus_name == name));
+
+        let index = match current {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+
+        let bus_name = self.players[index].bus_name.clone();
+        self.switch_to(&bus_name)
+    }
+
+    /// Try to connect to a media player
+    fn connect(&mut self) -> Result<bool> {
+        let finder = PlayerFinder::new()
+            .context("Failed to create player finder")?;
+
+        let mut players = Self::find_players(&finder);
+
+        // When following activity, start with whatever is playing right now;
+        // otherwise try preferred players first
+        if self.follow_mode != FollowMode::MostRecentActive {
+            let best = players
+                .iter()
+                .enumerate()
+                .filter_map(|(index, p)| Some((self.preferred_rank(p.identity())?, index)))
+                .min();
+            if let Some((_, index)) = best {
+                self.set_player(players.swap_remove(index));
+                return Ok(true);
+            }
+        }
+
+        // Fall back to the active player, or the first available one
+        match Self::find_active(players) {
+            Some(player) => {
+                self.set_player(player);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Apply pending signal deltas to the cached state.
+    ///
+    /// Returns true if the state changed.
+    fn process_events(&mut self) -> bool {
+        let followed = self.follow_activity() | self.follow_preferred();
+        self.update_server();
+
+        let events = match self.listener {
+            Some(ref listener) => listener.drain(),
+            None => Vec::new(),
+        };
+
+        if events.is_empty() {
+            return followed;
+        }
+
+        self.signals_seen = true;
+
+        let mut needs_refresh = false;
+        let mut needs_resync = false;
+        for event in events {
+            match event {
+                PlayerEvent::PropertiesChanged { changed, invalidated } => {
+                    // Losing CanControl clears the other capabilities, so
+                    // regaining it can't be applied as a single field
+                    let control_changed = changed.contains_key("CanControl");
+                    let track_list_changed = changed.contains_key("HasTrackList");
+                    needs_refresh |= !invalidated.is_empty();
+                    needs_resync |= self
+                        .state
+                        .apply_event(PlayerEvent::PropertiesChanged { changed, invalidated });
+                    if control_changed || track_list_changed {
+                        self.refresh_caps();
+                    }
+                    if track_list_changed {
+                        self.refresh_tracks();
+                    }
+                }
+                PlayerEvent::TrackListReplaced(ids) => {
+                    self.load_tracks(&ids);
+                }
+                PlayerEvent::TrackListChanged => {
+                    self.refresh_tracks();
+                }
+                PlayerEvent::PlaylistsChanged => {
+                    self.state.active_playlist = self.read_active_playlist();
+                }
+                PlayerEvent::PlaylistChanged(playlist) => {
+                    if let Some(slot) = self.playlists.iter_mut().find(|p| p.id == playlist.id) {
+                        *slot = playlist.clone();
+                    }
+                    if let Some(ref mut active) = self.state.active_playlist {
+                        if active.id == playlist.id {
+                            *active = playlist;
+                        }
+                    }
+                }
+                PlayerEvent::Quit => {
+                    self.player = None;
+                    self.listener = None;
+                    needs_refresh = true;
+                }
+                event => {
+                    needs_resync |= self.state.apply_event(event);
+                }
+            }
+        }
+
+        // Invalidated properties carry no value, so re-read everything
+        if needs_refresh {
+            self.get_state();
+        } else if needs_resync {
+            self.refresh_position();
+        }
+
+        true
+    }
+
+    /// Jump to a track in the TrackList
+    fn go_to(&mut self, index: usize) -> Result<()> {
+        self.require(self.state.caps.has_track_list, "Track list")?;
+        if let (Some(ref player), Some(track)) = (&self.player, self.state.tracks.get(index)) {
+            let id = TrackID::new(track.id.as_str())
+                .map_err(|e| anyhow::anyhow!(e))?;
+            player.go_to(&id)
+                .context("Failed to go to track")?;
+        }
+        Ok(())
+    }
+
+    /// Remove a track from the TrackList
+    fn remove_track(&mut self, index: usize) -> Result<()> {
+        self.require(self.state.caps.can_edit_tracks, "Removing tracks")?;
+        if let (Some(ref player), Some(track)) = (&self.player, self.state.tracks.get(index)) {
+            let id = TrackID::new(track.id.as_str())
+                .map_err(|e| anyhow::anyhow!(e))?;
+            player.remove_track(&id)
+                .context("Failed to remove track")?;
+        }
+        Ok(())
+    }
+
+    /// Re-sample the playback position to correct interpolation drift
+    fn refresh_position(&mut self) -> PlayerState {
+        if let Some(ref player) = self.player {
+            if let Ok(position) = player.get_position() {
+                self.state.sample_position(position);
+            }
+        }
+        self.state.clone()
+    }
+
     /// Get current player state
-    pub fn get_state(&mut self) -> PlayerState {
+    fn get_state(&mut self) -> PlayerState {
         if !self.ensure_connected() {
-            return PlayerState::default();
+            self.state = PlayerState::default();
+            return self.state.clone();
         }
 
         let player = match &self.player {
             Some(p) => p,
-            None => return PlayerState::default(),
+            None => {
+                self.state = PlayerState::default();
+                return self.state.clone();
+            }
         };
 
         let metadata = player.get_metadata().ok();
@@ -142,22 +1196,122 @@ impl MprisClient {
             .unwrap_or(1.0)
             .clamp(0.0, 1.0);
 
-        PlayerState {
+        let min_rate = player.get_minimum_playback_rate().unwrap_or(1.0);
+        let max_rate = player.get_maximum_playback_rate().unwrap_or(1.0);
+        let rate = player
+            .get_playback_rate()
+            .ok()
+            .filter(|r| valid_rate(*r, min_rate, max_rate))
+            .unwrap_or(1.0);
+        let shuffle = player.get_shuffle().unwrap_or(false);
+        let loop_status = player
+            .get_loop_status()
+            .map(Loop::from)
+            .unwrap_or_default();
+
+        self.state = PlayerState {
             connected: true,
             player_name: player.identity().to_string(),
+            desktop_entry: player.get_desktop_entry().ok().flatten().unwrap_or_default(),
+            fullscreen: player.get_fullscreen().ok().flatten().unwrap_or(false),
             title: extract_title(&metadata),
             artists: extract_artists(&metadata),
             album: extract_album(&metadata),
             art_url: metadata.as_ref().and_then(|m| m.art_url().map(String::from)),
+            metadata: metadata.clone().unwrap_or_default(),
             status,
             position,
+            position_sampled_at: Some(Instant::now()),
+            rate,
+            min_rate,
+            max_rate,
+            shuffle,
+            loop_status,
             length,
             volume,
+            caps: Capabilities::from_player(player),
+            track_id: metadata
+                .as_ref()
+                .and_then(|m| m.track_id())
+                .map(|id| id.as_str().to_string()),
+            tracks: std::mem::take(&mut self.state.tracks),
+            active_playlist: None,
+        };
+        self.state.caps.has_playlists = self.supports_playlists();
+        if self.state.caps.has_playlists {
+            self.state.active_playlist = self.read_active_playlist();
         }
+        self.refresh_tracks();
+        self.state.clone()
+    }
+
+    /// Playlists fetched by the last `refresh_playlists`
+    fn playlists(&self) -> &[Playlist] {
+        &self.playlists
+    }
+
+    /// Fetch up to `max_count` playlists from the connected player.
+    ///
+    /// Falls back to the player's first supported ordering if it doesn't
+    /// offer `order`.
+    fn refresh_playlists(
+        &mut self,
+        order: PlaylistOrder,
+        reverse: bool,
+        max_count: u32,
+    ) -> Result<&[Playlist]> {
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+
+        self.require(self.state.caps.has_playlists, "Playlists")?;
+
+        let playlists = match self.proxy() {
+            Some(proxy) => {
+                let orderings: Vec<String> = proxy
+                    .get(PLAYLISTS_INTERFACE, "Orderings")
+                    .unwrap_or_default();
+                let requested = ordering_name(order);
+                let ordering = match orderings.first() {
+                    Some(first) if !orderings.iter().any(|o| o == requested) => first.as_str(),
+                    _ => requested,
+                };
+
+                let (playlists,): (Vec<RawPlaylist>,) = proxy
+                    .method_call(
+                        PLAYLISTS_INTERFACE,
+                        "GetPlaylists",
+                        (0u32, max_count, ordering, reverse),
+                    )
+                    .context("Failed to get playlists")?;
+                playlists.into_iter().map(Playlist::from_raw).collect()
+            }
+            None => Vec::new(),
+        };
+
+        self.playlists = playlists;
+        Ok(&self.playlists)
+    }
+
+    /// Start playing a playlist from the last `refresh_playlists`
+    fn activate_playlist(&mut self, index: usize) -> Result<()> {
+        self.require(self.state.caps.has_playlists, "Playlists")?;
+        if let (Some(proxy), Some(playlist)) = (self.proxy(), self.playlists.get(index)) {
+            let id = dbus::Path::new(playlist.id.as_str())
+                .map_err(|e| anyhow::anyhow!(e))?;
+            proxy
+                .method_call::<(), _, _, _>(PLAYLISTS_INTERFACE, "ActivatePlaylist", (id,))
+                .context("Failed to activate playlist")?;
+        }
+        Ok(())
     }
 
     /// Toggle play/pause
-    pub fn toggle(&mut self) -> Result<()> {
+    fn toggle(&mut self) -> Result<()> {
+        let caps = self.state.caps;
+        let allowed = match self.state.status {
+            Status::Playing => caps.can_pause,
+            _ => caps.can_play,
+        };
+        self.require(allowed, "Play/pause")?;
         if let Some(ref player) = self.player {
             player.play_pause()
                 .context("Failed to toggle playback")?;
@@ -166,7 +1320,8 @@ impl MprisClient {
     }
 
     /// Next track
-    pub fn next(&mut self) -> Result<()> {
+    fn next(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_go_next, "Next track")?;
         if let Some(ref player) = self.player {
             player.next()
                 .context("Failed to go to next track")?;
@@ -175,7 +1330,8 @@ impl MprisClient {
     }
 
     /// Previous track
-    pub fn prev(&mut self) -> Result<()> {
+    fn prev(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_go_previous, "Previous track")?;
         if let Some(ref player) = self.player {
             player.previous()
                 .context("Failed to go to previous track")?;
@@ -184,9 +1340,10 @@ impl MprisClient {
     }
 
     /// Seek forward by duration
-    pub fn seek_forward(&mut self, duration: Duration) -> Result<()> {
+    fn seek_forward(&mut self, duration: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking")?;
         if let Some(ref player) = self.player {
-            let offset = duration.as_micros() as i64;
+            let offset = seek_offset(duration)?;
             player.seek(offset)
                 .context("Failed to seek forward")?;
         }
@@ -194,9 +1351,10 @@ impl MprisClient {
     }
 
     /// Seek backward by duration
-    pub fn seek_backward(&mut self, duration: Duration) -> Result<()> {
+    fn seek_backward(&mut self, duration: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking")?;
         if let Some(ref player) = self.player {
-            let offset = -(duration.as_micros() as i64);
+            let offset = -seek_offset(duration)?;
             player.seek(offset)
                 .context("Failed to seek backward")?;
         }
@@ -204,7 +1362,8 @@ impl MprisClient {
     }
 
     /// Set position
-    pub fn set_position(&mut self, position: Duration) -> Result<()> {
+    fn set_position(&mut self, position: Duration) -> Result<()> {
+        self.require(self.state.caps.can_seek, "Seeking")?;
         if let Some(ref player) = self.player {
             if let Ok(metadata) = player.get_metadata() {
                 if let Some(track_id) = metadata.track_id() {
@@ -217,7 +1376,8 @@ impl MprisClient {
     }
 
     /// Adjust volume by delta
-    pub fn adjust_volume(&mut self, delta: f64) -> Result<()> {
+    fn adjust_volume(&mut self, delta: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control")?;
         if let Some(ref player) = self.player {
             let current = player.get_volume().unwrap_or(1.0);
             let new_volume = (current + delta).clamp(0.0, 1.0);
@@ -226,13 +1386,397 @@ impl MprisClient {
         }
         Ok(())
     }
+
+    fn set_volume(&mut self, level: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Volume control")?;
+        if let Some(ref player) = self.player {
+            player.set_volume(level.clamp(0.0, 1.0))
+                .context("Failed to set volume")?;
+        }
+        Ok(())
+    }
+
+    /// Toggle shuffle
+    fn toggle_shuffle(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Shuffle")?;
+        if let Some(ref player) = self.player {
+            let current = player.get_shuffle().unwrap_or(self.state.shuffle);
+            player.set_shuffle(!current)
+                .context("Failed to toggle shuffle")?;
+        }
+        Ok(())
+    }
+
+    /// Cycle loop status None → Track → Playlist
+    fn cycle_loop(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Loop")?;
+        if let Some(ref player) = self.player {
+            let current = player
+                .get_loop_status()
+                .map(Loop::from)
+                .unwrap_or(self.state.loop_status);
+            player.set_loop_status(current.next().into())
+                .context("Failed to set loop status")?;
+        }
+        Ok(())
+    }
+
+    /// Adjust playback rate by delta, within the player's supported range
+    fn adjust_rate(&mut self, delta: f64) -> Result<()> {
+        self.require(self.state.caps.can_control, "Playback rate")?;
+        if let Some(ref player) = self.player {
+            let current = player.get_playback_rate().unwrap_or(1.0);
+            let min = player.get_minimum_playback_rate().unwrap_or(1.0);
+            let max = player.get_maximum_playback_rate().unwrap_or(1.0);
+            let new_rate = (current + delta).clamp(min, max.max(min));
+            player.set_playback_rate(new_rate)
+                .context("Failed to adjust playback rate")?;
+        }
+        Ok(())
+    }
+
+    /// Reset playback rate to normal speed
+    fn reset_rate(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_control, "Playback rate")?;
+        if let Some(ref player) = self.player {
+            player.set_playback_rate(1.0)
+                .context("Failed to reset playback rate")?;
+        }
+        Ok(())
+    }
+
+    /// Bring the player's user interface to the front
+    fn raise(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_raise, "Raise")?;
+        if let Some(ref player) = self.player {
+            player.raise()
+                .context("Failed to raise player")?;
+        }
+        Ok(())
+    }
+
+    /// Ask the player to exit
+    fn quit_player(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_quit, "Quit")?;
+        if let Some(ref player) = self.player {
+            player.quit()
+                .context("Failed to quit player")?;
+        }
+        Ok(())
+    }
+
+    /// Toggle the player's fullscreen mode
+    fn toggle_fullscreen(&mut self) -> Result<()> {
+        self.require(self.state.caps.can_set_fullscreen, "Fullscreen")?;
+        if let Some(ref player) = self.player {
+            player.set_fullscreen(!self.state.fullscreen)
+                .context("Failed to set fullscreen")?;
+        }
+        Ok(())
+    }
+
+    /// URI schemes the connected player can open (`file`, `http`, ...)
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        self.player
+            .as_ref()
+            .and_then(|p| p.get_supported_uri_schemes().ok())
+            .unwrap_or_default()
+    }
+
+    /// MIME types the connected player can open
+    fn supported_mime_types(&self) -> Vec<String> {
+        self.player
+            .as_ref()
+            .and_then(|p| p.get_supported_mime_types().ok())
+            .unwrap_or_default()
+    }
+
+    /// Ask the player to open and play a URI
+    fn open_uri(&mut self, uri: &str) -> Result<()> {
+        let schemes = self.supported_uri_schemes();
+        self.require(!schemes.is_empty(), "Opening URIs")?;
+
+        let scheme = uri
+            .split_once(':')
+            .map(|(scheme, _)| scheme)
+            .with_context(|| format!("Not a URI: {}", uri))?;
+        if !schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+            anyhow::bail!("{}: URIs not supported by {}", scheme, self.state.player_name);
+        }
+
+        if let Some(proxy) = self.proxy() {
+            proxy
+                .method_call::<(), _, _, _>(PLAYER_INTERFACE, "OpenUri", (uri,))
+                .context("Failed to open URI")?;
+        }
+        Ok(())
+    }
+}
+
+/// Seek offset in the microseconds MPRIS expects
+fn seek_offset(duration: Duration) -> Result<i64> {
+    i64::try_from(duration.as_micros()).map_err(|_| anyhow::anyhow!("Seek offset too large"))
+}
+
+/// Whether a reported playback rate is usable for interpolation
+fn valid_rate(rate: f64, min_rate: f64, max_rate: f64) -> bool {
+    rate.is_finite() && (min_rate..=max_rate).contains(&rate)
+}
+
+impl PlayerState {
+    /// Position extrapolated from the last sample using the playback rate
+    pub fn current_position(&self) -> Duration {
+        let sampled_at = match self.position_sampled_at {
+            Some(t) if self.status == Status::Playing => t,
+            _ => return self.position,
+        };
+
+        let elapsed = sampled_at.elapsed().as_secs_f64() * self.rate.max(0.0);
+        let position = Duration::try_from_secs_f64(elapsed)
+            .ok()
+            .and_then(|elapsed| self.position.checked_add(elapsed))
+            .unwrap_or(self.position);
+
+        if self.length > Duration::ZERO {
+            position.min(self.length)
+        } else {
+            position
+        }
+    }
+
+    /// Record a fresh position sample
+    fn sample_position(&mut self, position: Duration) {
+        self.position = position;
+        self.position_sampled_at = Some(Instant::now());
+    }
+
+    /// Apply an event that only changes cached state; events that need the
+    /// player to be re-read are ignored.
+    ///
+    /// Returns true if the position should be re-sampled from the player.
+    pub(crate) fn apply_event(&mut self, event: PlayerEvent) -> bool {
+        match event {
+            PlayerEvent::PropertiesChanged { changed, .. } => {
+                let mut needs_resync = false;
+                for (name, value) in changed {
+                    needs_resync |= self.apply_property(&name, value);
+                }
+                return needs_resync;
+            }
+            PlayerEvent::Seeked(position) => {
+                self.sample_position(micros_to_duration(position));
+            }
+            PlayerEvent::TrackAdded { metadata, after } => {
+                if let Some(track) = Track::from_metadata(Metadata::from(metadata)) {
+                    let index = self
+                        .tracks
+                        .iter()
+                        .position(|t| t.id == after)
+                        .map_or(0, |i| i + 1);
+                    self.tracks.insert(index, track);
+                }
+            }
+            PlayerEvent::TrackRemoved(id) => {
+                self.tracks.retain(|t| t.id != id);
+            }
+            PlayerEvent::TrackMetadataChanged { id, metadata } => {
+                let track = Track::from_metadata(Metadata::from(metadata));
+                let slot = self.tracks.iter_mut().find(|t| t.id == id);
+                if let (Some(slot), Some(track)) = (slot, track) {
+                    *slot = track;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Apply a single property from a `PropertiesChanged` signal.
+    ///
+    /// Returns true if the position should be re-sampled from the player.
+    fn apply_property(&mut self, name: &str, value: MetadataValue) -> bool {
+        match name {
+            "PlaybackStatus" => {
+                if let Some(status) = value.as_str().and_then(Status::from_name) {
+                    if status != self.status {
+                        // Freeze the interpolated position before the status flips
+                        self.sample_position(self.current_position());
+                        self.status = status;
+                        return true;
+                    }
+                }
+            }
+            "Rate" => {
+                if let Some(rate) = value.as_f64().filter(|r| valid_rate(*r, self.min_rate, self.max_rate)) {
+                    self.sample_position(self.current_position());
+                    self.rate = rate;
+                }
+            }
+            "MinimumRate" => {
+                if let Some(rate) = value.as_f64() {
+                    self.min_rate = rate;
+                }
+            }
+            "MaximumRate" => {
+                if let Some(rate) = value.as_f64() {
+                    self.max_rate = rate;
+                }
+            }
+            "Shuffle" => {
+                if let Some(shuffle) = value.as_bool() {
+                    self.shuffle = shuffle;
+                }
+            }
+            "LoopStatus" => {
+                if let Some(loop_status) = value.as_str().and_then(Loop::from_name) {
+                    self.loop_status = loop_status;
+                }
+            }
+            name if name.starts_with("Can") || name == "HasTrackList" => {
+                if let Some(value) = value.as_bool() {
+                    self.caps.set(name, value);
+                    if !self.caps.has_track_list {
+                        self.tracks.clear();
+                    }
+                }
+            }
+            "Metadata" => {
+                if let Some(map) = value.into_map() {
+                    self.metadata = Metadata::from(map);
+                    let metadata = Some(self.metadata.clone());
+                    self.track_id = metadata
+                        .as_ref()
+                        .and_then(|m| m.track_id())
+                        .map(|id| id.as_str().to_string());
+                    self.title = extract_title(&metadata);
+                    self.artists = extract_artists(&metadata);
+                    self.album = extract_album(&metadata);
+                    self.art_url = metadata.as_ref().and_then(|m| m.art_url().map(String::from));
+                    self.length = metadata
+                        .as_ref()
+                        .and_then(|m| m.length())
+                        .unwrap_or(Duration::ZERO);
+                    return true;
+                }
+            }
+            "Volume" => {
+                if let Some(volume) = value.as_f64() {
+                    self.volume = volume.clamp(0.0, 1.0);
+                }
+            }
+            "Position" => {
+                if let Some(position) = value.as_i64() {
+                    self.sample_position(micros_to_duration(position));
+                }
+            }
+            "Identity" => {
+                if let Some(identity) = value.as_str() {
+                    self.player_name = identity.to_string();
+                }
+            }
+            "DesktopEntry" => {
+                if let Some(entry) = value.as_str() {
+                    self.desktop_entry = entry.to_string();
+                }
+            }
+            "Fullscreen" => {
+                if let Some(fullscreen) = value.as_bool() {
+                    self.fullscreen = fullscreen;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+}
+
+/// Typed accessors for common xesam fields of the current track
+impl PlayerState {
+    pub fn track_number(&self) -> Option<i32> {
+        self.metadata.track_number()
+    }
+
+    pub fn disc_number(&self) -> Option<i32> {
+        self.metadata.disc_number()
+    }
+
+    pub fn album_artists(&self) -> Vec<&str> {
+        self.metadata.album_artists().unwrap_or_default()
+    }
+
+    pub fn genres(&self) -> Vec<&str> {
+        self.string_list("xesam:genre")
+    }
+
+    pub fn composers(&self) -> Vec<&str> {
+        self.string_list("xesam:composer")
+    }
+
+    /// `xesam:contentCreated`, an ISO 8601 date
+    pub fn content_created(&self) -> Option<&str> {
+        self.metadata.get("xesam:contentCreated")?.as_str()
+    }
+
+    /// `xesam:userRating`, from 0.0 to 1.0
+    pub fn user_rating(&self) -> Option<f64> {
+        self.metadata.get("xesam:userRating")?.as_f64()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.metadata.url()
+    }
+
+    /// `xesam:asText`, usually lyrics
+    pub fn lyrics(&self) -> Option<&str> {
+        self.metadata.get("xesam:asText")?.as_str()
+    }
+
+    /// Any metadata field formatted for display, or None if missing
+    pub fn meta(&self, key: &str) -> Option<String> {
+        self.metadata.get(key).map(format_metadata_value)
+    }
+
+    /// A field the spec defines as a string list; some players send a bare string
+    fn string_list(&self, key: &str) -> Vec<&str> {
+        match self.metadata.get(key) {
+            Some(MetadataValue::String(s)) => vec![s.as_str()],
+            Some(value) => value.as_str_array().unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Display form of a metadata value; lists are comma-separated
+pub fn format_metadata_value(value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::String(s) => s.clone(),
+        MetadataValue::I16(n) => n.to_string(),
+        MetadataValue::I32(n) => n.to_string(),
+        MetadataValue::I64(n) => n.to_string(),
+        MetadataValue::U8(n) => n.to_string(),
+        MetadataValue::U16(n) => n.to_string(),
+        MetadataValue::U32(n) => n.to_string(),
+        MetadataValue::U64(n) => n.to_string(),
+        MetadataValue::F64(n) => n.to_string(),
+        MetadataValue::Bool(b) => b.to_string(),
+        MetadataValue::Array(values) => values
+            .iter()
+            .map(format_metadata_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        MetadataValue::Map(_) | MetadataValue::Unsupported => String::new(),
+    }
+}
+
+fn micros_to_duration(micros: i64) -> Duration {
+    Duration::from_micros(micros.max(0) as u64)
 }
 
 fn extract_title(metadata: &Option<Metadata>) -> String {
     metadata
         .as_ref()
         .and_then(|m| m.title().map(String::from))
-        .unwrap_or_else(|| "Unknown".into())
+        .unwrap_or_default()
 }
 
 fn extract_artists(metadata: &Option<Metadata>) -> String {
@@ -240,14 +1784,25 @@ fn extract_artists(metadata: &Option<Metadata>) -> String {
         .as_ref()
         .and_then(|m| m.artists())
         .map(|a| a.join(", "))
-        .unwrap_or_else(|| "Unknown Artist".into())
+        .unwrap_or_default()
 }
 
 fn extract_album(metadata: &Option<Metadata>) -> String {
     metadata
         .as_ref()
         .and_then(|m| m.album_name().map(String::from))
-        .unwrap_or_else(|| "Unknown Album".into())
+        .unwrap_or_default()
+}
+
+/// MPRIS name of a playlist ordering
+fn ordering_name(order: PlaylistOrder) -> &'static str {
+    match order {
+        PlaylistOrder::Alphabetical => "Alphabetical",
+        PlaylistOrder::Created => "CreationDate",
+        PlaylistOrder::Modified => "ModifiedDate",
+        PlaylistOrder::Played => "LastPlayDate",
+        PlaylistOrder::User => "UserDefined",
+    }
 }
 
 /// Format duration as MM:SS
@@ -257,3 +1812,38 @@ pub fn format_duration(d: Duration) -> String {
     let secs = secs % 60;
     format!("{:02}:{:02}", mins, secs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(bus_name: &str, identity: &str) -> PlayerSummary {
+        PlayerSummary {
+            bus_name: bus_name.into(),
+            unique_name: String::new(),
+            identity: identity.into(),
+            status: Status::Stopped,
+            title: String::new(),
+        }
+    }
+
+    #[test]
+    fn prefers_exact_player_names() {
+        let players = [
+            summary("org.mpris.MediaPlayer2.spotify", "Spotify"),
+            summary("org.mpris.MediaPlayer2.vlc.instance42", "VLC media player"),
+            summary("org.mpris.MediaPlayer2.vlc", "VLC media player"),
+            summary("org.mpris.MediaPlayer2.mpv", "mpv"),
+        ];
+        let pick = |name| best_match(&players, name).map(|p| p.bus_name.as_str());
+
+        let instance = "org.mpris.MediaPlayer2.vlc.instance42";
+        assert_eq!(pick(instance), Some(instance));
+        assert_eq!(pick("vlc"), Some("org.mpris.MediaPlayer2.vlc"));
+        assert_eq!(pick("MPV"), Some("org.mpris.MediaPlayer2.mpv"));
+        // A lone letter goes to the player it starts, not the first containing it
+        assert_eq!(pick("m"), Some("org.mpris.MediaPlayer2.mpv"));
+        assert_eq!(pick("tify"), Some("org.mpris.MediaPlayer2.spotify"));
+        assert_eq!(pick("rhythmbox"), None);
+    }
+}
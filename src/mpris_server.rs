@@ -0,0 +1,364 @@
+//! Our own MPRIS player: a stand-in that mirrors whichever player mplay follows.
+//!
+//! Method calls and property reads are forwarded to the followed player and
+//! its signals are re-emitted under our name, so tools that talk to
+//! `org.mpris.MediaPlayer2.mplay` control the same player as the TUI.
+//! Forwarded calls are answered once the player replies, on a later
+//! [`MprisServer::process`], so a slow player never stalls the UI.
+
+use anyhow::{Context, Result};
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::RequestNameReply;
+use dbus::blocking::LocalConnection;
+use dbus::channel::{MatchingReceiver, Sender, Token};
+use dbus::message::{MatchRule, MessageType};
+use dbus::strings::{ErrorName, Interface, Member, Path};
+use dbus::Message;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::mpris_client::{DBUS_TIMEOUT, MPRIS_PATH, PLAYER_INTERFACE, ROOT_INTERFACE};
+
+/// Bus name the mirror registers
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.mplay";
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+const NO_PLAYER_ERROR: &str = "org.freedesktop.DBus.Error.Failed";
+const NO_REPLY_ERROR: &str = "org.freedesktop.DBus.Error.NoReply";
+
+/// Introspection data served while no player is followed
+const IDLE_INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.freedesktop.DBus.Properties">
+    <method name="Get">
+      <arg name="interface" type="s" direction="in"/>
+      <arg name="property" type="s" direction="in"/>
+      <arg name="value" type="v" direction="out"/>
+    </method>
+    <method name="GetAll">
+      <arg name="interface" type="s" direction="in"/>
+      <arg name="properties" type="a{sv}" direction="out"/>
+    </method>
+    <signal name="PropertiesChanged">
+      <arg name="interface" type="s"/>
+      <arg name="changed" type="a{sv}"/>
+      <arg name="invalidated" type="as"/>
+    </signal>
+  </interface>
+  <interface name="org.mpris.MediaPlayer2"/>
+  <interface name="org.mpris.MediaPlayer2.Player"/>
+</node>
+"#;
+
+/// Messages queued by the connection's receive callbacks
+#[derive(Default)]
+struct Inbox {
+    /// Method calls addressed to the mirror
+    calls: VecDeque<Message>,
+    /// Signals from the followed player, to re-emit under our name
+    signals: VecDeque<Message>,
+    /// Method returns and errors, some answering forwarded calls
+    replies: VecDeque<Message>,
+}
+
+/// A call passed on to the followed player, awaiting its reply
+struct Forwarded {
+    call: Message,
+    sent: Instant,
+}
+
+/// Owner of `org.mpris.MediaPlayer2.mplay` on the session bus
+pub struct MprisServer {
+    conn: LocalConnection,
+    inbox: Rc<RefCell<Inbox>>,
+    /// Unique name of the followed player
+    target: Option<String>,
+    /// Subscription to the followed player's signals
+    relay: Option<Token>,
+    /// Forwarded calls by the serial of the copy sent to the player
+    pending: HashMap<u32, Forwarded>,
+    /// Interfaces whose properties were asked of a newly followed player,
+    /// by the serial of our `GetAll`
+    refreshing: HashMap<u32, String>,
+}
+
+impl MprisServer {
+    /// Claim the bus name and start accepting calls
+    pub fn new() -> Result<Self> {
+        let conn = LocalConnection::new_session()
+            .context("Failed to open D-Bus session connection")?;
+
+        let reply = conn
+            .request_name(BUS_NAME, false, false, true)
+            .context("Failed to request MPRIS bus name")?;
+        if reply != RequestNameReply::PrimaryOwner {
+            anyhow::bail!("{} is already owned by another process", BUS_NAME);
+        }
+
+        let inbox = Rc::new(RefCell::new(Inbox::default()));
+        let calls = Rc::clone(&inbox);
+        conn.start_receive(
+            MatchRule::new_method_call().with_path(MPRIS_PATH),
+            Box::new(move |msg, _| {
+                calls.borrow_mut().calls.push_back(msg);
+                true
+            }),
+        );
+        for msg_type in [MessageType::MethodReturn, MessageType::Error] {
+            let replies = Rc::clone(&inbox);
+            let mut rule = MatchRule::new();
+            rule.msg_type = Some(msg_type);
+            conn.start_receive(
+                rule,
+                Box::new(move |msg, _| {
+                    replies.borrow_mut().replies.push_back(msg);
+                    true
+                }),
+            );
+        }
+
+        Ok(Self {
+            conn,
+            inbox,
+            target: None,
+            relay: None,
+            pending: HashMap::new(),
+            refreshing: HashMap::new(),
+        })
+    }
+
+    /// Mirror the player with this unique name, or nothing.
+    ///
+    /// Clients are sent the new player's properties as a change once it
+    /// reports them, so they pick up the switch without re-reading everything.
+    pub fn follow(&mut self, unique_name: Option<&str>) {
+        if self.target.as_deref() == unique_name {
+            return;
+        }
+
+        if let Some(token) = self.relay.take() {
+            let _ = self.conn.remove_match(token);
+        }
+        self.inbox.borrow_mut().signals.clear();
+        self.target = unique_name.map(str::to_string);
+
+        if let Some(name) = unique_name {
+            let rule = MatchRule::new()
+                .with_type(MessageType::Signal)
+                .with_sender(name.to_string())
+                .with_path(MPRIS_PATH);
+            let signals = Rc::clone(&self.inbox);
+            self.relay = self
+                .conn
+                .add_match(rule, move |(): (), _, msg| {
+                    if let Ok(copy) = msg.duplicate() {
+                        signals.borrow_mut().signals.push_back(copy);
+                    }
+                    true
+                })
+                .ok();
+        }
+
+        self.refreshing.clear();
+        for interface in [ROOT_INTERFACE, PLAYER_INTERFACE] {
+            match unique_name {
+                Some(name) => self.request_properties(name, interface),
+                None => self.emit_properties_changed(interface, idle_properties(interface)),
+            }
+        }
+    }
+
+    /// Answer queued calls and relay queued signals without blocking
+    pub fn process(&mut self) {
+        while let Ok(true) = self.conn.process(Duration::ZERO) {}
+
+        loop {
+            let signal = self.inbox.borrow_mut().signals.pop_front();
+            let Some(mut signal) = signal else {
+                break;
+            };
+            signal.set_destination(None);
+            let _ = self.conn.send(signal);
+        }
+
+        loop {
+            let call = self.inbox.borrow_mut().calls.pop_front();
+            let Some(call) = call else {
+                break;
+            };
+            let no_reply = call.get_no_reply();
+            let reply = match self.target.clone() {
+                Some(target) => self.forward(call, &target),
+                None => Some(idle_reply(&call)),
+            };
+            if let Some(reply) = reply.filter(|_| !no_reply) {
+                let _ = self.conn.send(reply);
+            }
+        }
+
+        loop {
+            let reply = self.inbox.borrow_mut().replies.pop_front();
+            let Some(mut reply) = reply else {
+                break;
+            };
+            let refreshed = reply
+                .get_reply_serial()
+                .and_then(|serial| self.refreshing.remove(&serial));
+            if let Some(interface) = refreshed {
+                if let Ok(properties) = reply.read1::<PropMap>() {
+                    self.emit_properties_changed(&interface, properties);
+                }
+                continue;
+            }
+            let Some(forwarded) = reply
+                .get_reply_serial()
+                .and_then(|serial| self.pending.remove(&serial))
+            else {
+                continue;
+            };
+            let call = forwarded.call;
+            let ours = match reply.as_result() {
+                Ok(reply) => {
+                    let mut ours = call.method_return();
+                    ours.append_items(&reply.get_items());
+                    ours
+                }
+                Err(e) => error_reply(
+                    &call,
+                    e.name().unwrap_or(NO_PLAYER_ERROR),
+                    e.message().unwrap_or_default(),
+                ),
+            };
+            let _ = self.conn.send(ours);
+        }
+
+        // The bus normally answers for a player that never replies, but
+        // don't hold on to calls forever if it doesn't
+        let now = Instant::now();
+        let expired: Vec<u32> = self
+            .pending
+            .iter()
+            .filter(|(_, forwarded)| now.duration_since(forwarded.sent) > DBUS_TIMEOUT)
+            .map(|(&serial, _)| serial)
+            .collect();
+        for serial in expired {
+            if let Some(forwarded) = self.pending.remove(&serial) {
+                let reply = error_reply(&forwarded.call, NO_REPLY_ERROR, "The player did not reply");
+                let _ = self.conn.send(reply);
+            }
+        }
+    }
+
+    /// Send a copy of `call` to `target`; its reply is relayed as ours once
+    /// it arrives. Returns an immediate reply if the call can't be forwarded.
+    fn forward(&mut self, call: Message, target: &str) -> Option<Message> {
+        let mut copy = match call.duplicate() {
+            Ok(copy) => copy,
+            Err(e) => return Some(error_reply(&call, NO_PLAYER_ERROR, &e)),
+        };
+        copy.set_destination(Some(target.into()));
+
+        match self.conn.send(copy) {
+            Ok(serial) => {
+                if !call.get_no_reply() {
+                    let sent = Instant::now();
+                    self.pending.insert(serial, Forwarded { call, sent });
+                }
+                None
+            }
+            Err(()) => Some(error_reply(&call, NO_PLAYER_ERROR, "Failed to forward the call")),
+        }
+    }
+
+    /// Ask `target` for every property of `interface`; they are announced
+    /// when the reply arrives in `process`
+    fn request_properties(&mut self, target: &str, interface: &str) {
+        let Ok(call) = Message::new_method_call(target, MPRIS_PATH, PROPERTIES_INTERFACE, "GetAll")
+        else {
+            return;
+        };
+        if let Ok(serial) = self.conn.send(call.append1(interface)) {
+            self.refreshing.insert(serial, interface.to_string());
+        }
+    }
+
+    fn emit_properties_changed(&self, interface: &str, changed: PropMap) {
+        let signal = Message::signal(
+            &Path::from(MPRIS_PATH),
+            &Interface::from(PROPERTIES_INTERFACE),
+            &Member::from("PropertiesChanged"),
+        )
+        .append3(interface, changed, Vec::<String>::new());
+        let _ = self.conn.send(signal);
+    }
+}
+
+/// Answer a call ourselves while no player is followed
+fn idle_reply(call: &Message) -> Message {
+    let interface = call.interface();
+    let member = call.member();
+    match (interface.as_deref(), member.as_deref()) {
+        (Some(INTROSPECTABLE_INTERFACE), Some("Introspect")) => {
+            call.return_with_args((IDLE_INTROSPECTION,))
+        }
+        (Some(PROPERTIES_INTERFACE), Some("GetAll")) => match call.read1::<&str>() {
+            Ok(interface) => call.return_with_args((idle_properties(interface),)),
+            Err(e) => error_reply(call, "org.freedesktop.DBus.Error.InvalidArgs", &e.to_string()),
+        },
+        (Some(PROPERTIES_INTERFACE), Some("Get")) => {
+            let value = call
+                .read2::<&str, &str>()
+                .ok()
+                .and_then(|(interface, name)| idle_properties(interface).remove(name));
+            match value {
+                Some(value) => call.return_with_args((value,)),
+                None => error_reply(call, "org.freedesktop.DBus.Error.UnknownProperty", "No such property"),
+            }
+        }
+        _ => error_reply(call, NO_PLAYER_ERROR, "mplay is not following a player"),
+    }
+}
+
+/// Properties of a stopped player that can't be controlled
+fn idle_properties(interface: &str) -> PropMap {
+    fn prop(value: impl RefArg + 'static) -> Variant<Box<dyn RefArg>> {
+        Variant(Box::new(value))
+    }
+
+    let mut props = PropMap::new();
+    match interface {
+        ROOT_INTERFACE => {
+            props.insert("Identity".into(), prop("mplay".to_string()));
+            props.insert("CanQuit".into(), prop(false));
+            props.insert("CanRaise".into(), prop(false));
+            props.insert("HasTrackList".into(), prop(false));
+            props.insert("SupportedUriSchemes".into(), prop(Vec::<String>::new()));
+            props.insert("SupportedMimeTypes".into(), prop(Vec::<String>::new()));
+        }
+        PLAYER_INTERFACE => {
+            props.insert("PlaybackStatus".into(), prop("Stopped".to_string()));
+            props.insert("Metadata".into(), prop(PropMap::new()));
+            props.insert("Position".into(), prop(0i64));
+            props.insert("Rate".into(), prop(1.0));
+            props.insert("MinimumRate".into(), prop(1.0));
+            props.insert("MaximumRate".into(), prop(1.0));
+            props.insert("Volume".into(), prop(1.0));
+            for name in ["CanControl", "CanPlay", "CanPause", "CanSeek", "CanGoNext", "CanGoPrevious"] {
+                props.insert(name.into(), prop(false));
+            }
+        }
+        _ => {}
+    }
+    props
+}
+
+fn error_reply(call: &Message, name: &str, message: &str) -> Message {
+    let name = ErrorName::new(name).unwrap_or_else(|_| ErrorName::from(NO_PLAYER_ERROR));
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    call.error(&name, &message)
+}
@@ -0,0 +1,990 @@
+//! Template language for label text and `mplay print` formats.
+//!
+//! `{title}` inserts a variable, `{title|upper|truncate(30)}` passes it
+//! through filters, `{position:h:mm:ss}` formats a duration and
+//! `{if shuffle}…{else}…{end}` renders text conditionally. `{{` and `}}` are
+//! literal braces. The older `$title` form still works as a bare variable.
+//!
+//! Templates are parsed when the config is loaded, so mistakes are reported
+//! up front instead of showing up as raw text in the UI. Queue entries use a
+//! `TrackTemplate`, which has the track's own variables instead.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::mpris_client::{format_duration, Loop, PlayerState, Status, Track};
+
+/// What a variable holds, which decides the formats it accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Duration,
+    /// Renders as `on` / `off`
+    Bool,
+}
+
+/// Every variable name and its kind; `meta[key]` is handled separately
+const VARIABLES: &[(&str, Kind)] = &[
+    ("title", Kind::Text),
+    ("artists", Kind::Text),
+    ("artist", Kind::Text),
+    ("album-artist", Kind::Text),
+    ("album", Kind::Text),
+    ("track-number", Kind::Text),
+    ("disc-number", Kind::Text),
+    ("genre", Kind::Text),
+    ("composer", Kind::Text),
+    ("date", Kind::Text),
+    ("year", Kind::Text),
+    ("rating", Kind::Text),
+    ("url", Kind::Text),
+    ("lyrics", Kind::Text),
+    ("status-icon", Kind::Text),
+    ("status", Kind::Text),
+    ("playing", Kind::Bool),
+    ("paused", Kind::Bool),
+    ("stopped", Kind::Bool),
+    ("position", Kind::Duration),
+    ("length", Kind::Duration),
+    ("remaining", Kind::Duration),
+    ("volume", Kind::Text),
+    ("playlist", Kind::Text),
+    ("player", Kind::Text),
+    ("identity", Kind::Text),
+    ("desktop-entry", Kind::Text),
+    ("shuffle", Kind::Bool),
+    ("loop", Kind::Text),
+    ("rate", Kind::Text),
+    ("message", Kind::Text),
+];
+
+/// Variables of a queue entry
+const TRACK_VARIABLES: &[(&str, Kind)] = &[
+    ("index", Kind::Text),
+    ("title", Kind::Text),
+    ("artists", Kind::Text),
+    ("artist", Kind::Text),
+    ("album", Kind::Text),
+    ("length", Kind::Duration),
+];
+
+/// Variables a template can refer to
+struct Scope {
+    variables: &'static [(&'static str, Kind)],
+    /// Whether `meta[key]` is available
+    meta: bool,
+}
+
+const PLAYER_SCOPE: Scope = Scope {
+    variables: VARIABLES,
+    meta: true,
+};
+
+const TRACK_SCOPE: Scope = Scope {
+    variables: TRACK_VARIABLES,
+    meta: false,
+};
+
+/// Template that failed to parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    message: String,
+    /// Character offset of the problem in the template
+    column: usize,
+    template: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {} of template {:?}", self.message, self.column + 1, self.template)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parsed template, kept alongside its source so configs round-trip
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Placeholder {
+        var: Var,
+        format: Option<DurationFormat>,
+        filters: Vec<Filter>,
+    },
+    If {
+        var: Var,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Var {
+    Named(&'static str, Kind),
+    /// Raw metadata field, `meta[xesam:genre]`
+    Meta(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    /// Cut to this many terminal columns, ending with `…`
+    Truncate(usize),
+    /// Pad with spaces at the end up to this many columns
+    Pad(usize),
+    /// Pad with spaces at the start up to this many columns
+    Lpad(usize),
+    /// Replacement for an empty value
+    Default(String),
+}
+
+/// Duration layout such as `h:mm:ss`: `h`, `m` and `s` are unpadded,
+/// doubled letters are zero-padded, anything else is copied
+#[derive(Debug, Clone, PartialEq)]
+struct DurationFormat(Vec<DurationPart>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum DurationPart {
+    Hours { padded: bool },
+    Minutes { padded: bool },
+    Seconds { padded: bool },
+    Literal(char),
+}
+
+/// Argument of a filter call
+enum Arg {
+    Number(usize),
+    Text(String),
+}
+
+/// Template for one queue entry: `{index}`, `{title}`, `{artists}`,
+/// `{album}` and `{length}` refer to that track
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TrackTemplate(Template);
+
+impl Template {
+    /// Parse `source`, reporting the first syntax error
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Self::parse_in(source, &PLAYER_SCOPE)
+    }
+
+    fn parse_in(source: &str, scope: &'static Scope) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            source,
+            chars: source.chars().collect(),
+            pos: 0,
+            scope,
+        };
+        let (nodes, end) = parser.parse_nodes()?;
+        if let Some(end) = end {
+            return Err(parser.error_at(end.column, format!("'{{{}}}' without a matching '{{if}}'", end.keyword)));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            nodes,
+        })
+    }
+
+    /// Text the template was parsed from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Fill in the template from the player state
+    pub fn render(&self, state: &PlayerState, message: &str) -> String {
+        self.render_with(&|var| value(var, state, message))
+    }
+
+    fn render_with(&self, lookup: &dyn Fn(&Var) -> Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, lookup, &mut out);
+        out
+    }
+}
+
+impl TrackTemplate {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Template::parse_in(source, &TRACK_SCOPE).map(Self)
+    }
+
+    /// Fill in the template for the track at `index` (counting from 0)
+    pub fn render(&self, track: &Track, index: usize) -> String {
+        self.0.render_with(&|var| track_value(var, track, index))
+    }
+}
+
+impl FromStr for Template {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl From<Template> for String {
+    fn from(template: Template) -> Self {
+        template.source
+    }
+}
+
+impl FromStr for TrackTemplate {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for TrackTemplate {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl From<TrackTemplate> for String {
+    fn from(template: TrackTemplate) -> Self {
+        template.0.source
+    }
+}
+
+/// `{else}` or `{end}` that closed a run of nodes
+struct BlockEnd {
+    keyword: &'static str,
+    column: usize,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    scope: &'static Scope,
+}
+
+impl Parser<'_> {
+    fn error_at(&self, column: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            message: message.into(),
+            column,
+            template: self.source.to_string(),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        self.error_at(self.pos, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn rest_starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", c)))
+        }
+    }
+
+    /// Parse until the end of input or an `{else}` / `{end}`, which is returned
+    fn parse_nodes(&mut self) -> Result<(Vec<Node>, Option<BlockEnd>), ParseError> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '{' if self.rest_starts_with("{{") => {
+                    text.push('{');
+                    self.pos += 2;
+                }
+                '}' if self.rest_starts_with("}}") => {
+                    text.push('}');
+                    self.pos += 2;
+                }
+                '}' => return Err(self.error("unmatched '}' (write '}}' for a literal brace)")),
+                '{' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    self.skip_spaces();
+
+                    if let Some(keyword) = self.keyword(&["else", "end"]) {
+                        self.expect('}')?;
+                        flush_text(&mut nodes, &mut text);
+                        return Ok((nodes, Some(BlockEnd { keyword, column: start })));
+                    }
+
+                    flush_text(&mut nodes, &mut text);
+                    if self.keyword(&["if"]).is_some() {
+                        nodes.push(self.parse_if(start)?);
+                    } else {
+                        nodes.push(self.parse_placeholder()?);
+                    }
+                }
+                '$' => match self.legacy_variable() {
+                    Some(var) => {
+                        flush_text(&mut nodes, &mut text);
+                        nodes.push(Node::Placeholder {
+                            var,
+                            format: None,
+                            filters: Vec::new(),
+                        });
+                    }
+                    None => {
+                        text.push('$');
+                        self.pos += 1;
+                    }
+                },
+                c => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        flush_text(&mut nodes, &mut text);
+        Ok((nodes, None))
+    }
+
+    /// Consume one of `keywords` if it stands alone as a word here
+    fn keyword(&mut self, keywords: &[&'static str]) -> Option<&'static str> {
+        let keyword = keywords.iter().find(|k| {
+            self.rest_starts_with(k)
+                && !self
+                    .chars
+                    .get(self.pos + k.len())
+                    .is_some_and(|&c| is_name_char(c))
+        })?;
+        self.pos += keyword.len();
+        Some(keyword)
+    }
+
+    /// `{if [!]var}…[{else}…]{end}`, after the `if`
+    fn parse_if(&mut self, start: usize) -> Result<Node, ParseError> {
+        self.skip_spaces();
+        let negate = if self.peek() == Some('!') {
+            self.pos += 1;
+            true
+        } else {
+            self.keyword(&["not"]).is_some()
+        };
+        self.skip_spaces();
+        let var = self.variable()?;
+        self.expect('}')?;
+
+        let unclosed = |parser: &Self| parser.error_at(start, "'{if}' without a matching '{end}'");
+
+        let (then, end) = self.parse_nodes()?;
+        let otherwise = match end {
+            Some(BlockEnd { keyword: "end", .. }) => Vec::new(),
+            Some(BlockEnd { keyword: "else", .. }) => match self.parse_nodes()? {
+                (otherwise, Some(BlockEnd { keyword: "end", .. })) => otherwise,
+                (_, Some(BlockEnd { column, .. })) => {
+                    return Err(self.error_at(column, "second '{else}' in the same '{if}'"))
+                }
+                (_, None) => return Err(unclosed(self)),
+            },
+            _ => return Err(unclosed(self)),
+        };
+
+        Ok(Node::If {
+            var,
+            negate,
+            then,
+            otherwise,
+        })
+    }
+
+    /// `{var[:format][|filter[(args)]]…}`, after the `{`
+    fn parse_placeholder(&mut self) -> Result<Node, ParseError> {
+        let var_start = self.pos;
+        let var = self.variable()?;
+        self.skip_spaces();
+
+        let format = if self.peek() == Some(':') {
+            self.pos += 1;
+            let format_start = self.pos;
+            let mut spec = String::new();
+            while let Some(c) = self.peek().filter(|&c| c != '|' && c != '}') {
+                spec.push(c);
+                self.pos += 1;
+            }
+            if !matches!(var, Var::Named(_, Kind::Duration)) {
+                return Err(self.error_at(var_start, "only durations take a format"));
+            }
+            Some(DurationFormat::parse(spec.trim()).ok_or_else(|| {
+                self.error_at(format_start, "duration formats need at least one of h, m or s")
+            })?)
+        } else {
+            None
+        };
+
+        let mut filters = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('|') => {
+                    self.pos += 1;
+                    filters.push(self.filter()?);
+                }
+                Some(_) => return Err(self.error("expected '|' or '}'")),
+                None => return Err(self.error("unclosed '{'")),
+            }
+        }
+
+        Ok(Node::Placeholder { var, format, filters })
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|&c| is_name_char(c)) {
+            name.push(c);
+            self.pos += 1;
+        }
+        name
+    }
+
+    fn variable(&mut self) -> Result<Var, ParseError> {
+        self.skip_spaces();
+        let start = self.pos;
+
+        if self.scope.meta && self.rest_starts_with("meta[") {
+            self.pos += "meta[".len();
+            let mut key = String::new();
+            loop {
+                match self.peek() {
+                    Some(']') => break,
+                    Some(c) => key.push(c),
+                    None => return Err(self.error_at(start, "unclosed 'meta['")),
+                }
+                self.pos += 1;
+            }
+            self.pos += 1;
+            return Ok(Var::Meta(key));
+        }
+
+        let name = self.name();
+        if name.is_empty() {
+            return Err(self.error("expected a variable name"));
+        }
+        self.scope
+            .variables
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|&(name, kind)| Var::Named(name, kind))
+            .ok_or_else(|| self.error_at(start, format!("unknown variable '{}'", name)))
+    }
+
+    /// `$name` or `$meta[key]`: the longest known name wins, so `$artists`
+    /// isn't read as `$artist` followed by `s`
+    fn legacy_variable(&mut self) -> Option<Var> {
+        let after = self.pos + 1;
+        let rest: String = self.chars[after..].iter().collect();
+
+        if self.scope.meta && rest.starts_with("meta[") {
+            let end = rest.find(']')?;
+            let key = rest["meta[".len()..end].to_string();
+            self.pos = after + rest[..=end].chars().count();
+            return Some(Var::Meta(key));
+        }
+
+        let &(name, kind) = self
+            .scope
+            .variables
+            .iter()
+            .filter(|(name, _)| rest.starts_with(name))
+            .max_by_key(|(name, _)| name.len())?;
+        self.pos = after + name.len();
+        Some(Var::Named(name, kind))
+    }
+
+    fn filter(&mut self) -> Result<Filter, ParseError> {
+        self.skip_spaces();
+        let start = self.pos;
+        let name = self.name();
+
+        self.skip_spaces();
+        let args = if self.peek() == Some('(') {
+            self.pos += 1;
+            self.arguments()?
+        } else {
+            Vec::new()
+        };
+
+        let number = |args: &[Arg]| match args {
+            [Arg::Number(n)] => Some(*n),
+            _ => None,
+        };
+        let filter = match name.as_str() {
+            "upper" if args.is_empty() => Filter::Upper,
+            "lower" if args.is_empty() => Filter::Lower,
+            "truncate" => number(&args).map(Filter::Truncate).ok_or_else(|| {
+                self.error_at(start, "truncate takes a length, e.g. truncate(30)")
+            })?,
+            "pad" => number(&args)
+                .map(Filter::Pad)
+                .ok_or_else(|| self.error_at(start, "pad takes a width, e.g. pad(20)"))?,
+            "lpad" => number(&args)
+                .map(Filter::Lpad)
+                .ok_or_else(|| self.error_at(start, "lpad takes a width, e.g. lpad(20)"))?,
+            "default" => match args.as_slice() {
+                [Arg::Text(text)] => Filter::Default(text.clone()),
+                _ => return Err(self.error_at(start, "default takes a string, e.g. default(\"-\")")),
+            },
+            "upper" | "lower" => {
+                return Err(self.error_at(start, format!("{} takes no arguments", name)))
+            }
+            "" => return Err(self.error("expected a filter name")),
+            _ => return Err(self.error_at(start, format!("unknown filter '{}'", name))),
+        };
+        Ok(filter)
+    }
+
+    /// Comma-separated numbers and quoted strings, after the `(`
+    fn arguments(&mut self) -> Result<Vec<Arg>, ParseError> {
+        let mut args = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(')') if args.is_empty() => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    args.push(Arg::Text(self.string()?));
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let start = self.pos;
+                    let mut digits = String::new();
+                    while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        self.pos += 1;
+                    }
+                    let n = digits.parse().map_err(|_| self.error_at(start, "number too large"))?;
+                    args.push(Arg::Number(n));
+                }
+                _ => return Err(self.error("expected a number or a quoted string")),
+            }
+
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                _ => return Err(self.error("expected ',' or ')'")),
+            }
+        }
+    }
+
+    /// String body after the opening quote, with `\"` and `\\` escapes
+    fn string(&mut self) -> Result<String, ParseError> {
+        let start = self.pos - 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') if matches!(self.chars.get(self.pos + 1), Some('"' | '\\')) => {
+                    text.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(self.error_at(start, "unclosed string")),
+            }
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+fn flush_text(nodes: &mut Vec<Node>, text: &mut String) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(std::mem::take(text)));
+    }
+}
+
+impl DurationFormat {
+    fn parse(spec: &str) -> Option<Self> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut parts = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let padded = chars.get(i + 1) == Some(&c);
+            let part = match c {
+                'h' => DurationPart::Hours { padded },
+                'm' => DurationPart::Minutes { padded },
+                's' => DurationPart::Seconds { padded },
+                c => {
+                    parts.push(DurationPart::Literal(c));
+                    i += 1;
+                    continue;
+                }
+            };
+            parts.push(part);
+            i += if padded { 2 } else { 1 };
+        }
+
+        let has_unit = parts.iter().any(|p| !matches!(p, DurationPart::Literal(_)));
+        has_unit.then_some(Self(parts))
+    }
+
+    /// Format `d`; the largest unit present isn't wrapped, so `m:ss` can
+    /// show `75:00`
+    fn format(&self, d: Duration) -> String {
+        let total = d.as_secs();
+        let has_hours = self.0.iter().any(|p| matches!(p, DurationPart::Hours { .. }));
+        let has_minutes = self.0.iter().any(|p| matches!(p, DurationPart::Minutes { .. }));
+
+        let hours = total / 3600;
+        let minutes = if has_hours { total / 60 % 60 } else { total / 60 };
+        let seconds = if has_hours || has_minutes { total % 60 } else { total };
+
+        let number = |n: u64, padded: bool| if padded { format!("{:02}", n) } else { n.to_string() };
+        self.0
+            .iter()
+            .map(|part| match *part {
+                DurationPart::Hours { padded } => number(hours, padded),
+                DurationPart::Minutes { padded } => number(minutes, padded),
+                DurationPart::Seconds { padded } => number(seconds, padded),
+                DurationPart::Literal(c) => c.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// A variable's value before formatting
+enum Value {
+    Text(String),
+    Duration(Duration),
+    Bool(bool),
+    /// Renders as text, but is false when looping is off
+    Loop(Loop),
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.is_empty(),
+            Value::Duration(d) => !d.is_zero(),
+            Value::Bool(b) => *b,
+            Value::Loop(status) => *status != Loop::None,
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], lookup: &dyn Fn(&Var) -> Value, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Placeholder { var, format, filters } => {
+                let mut text = match (lookup(var), format) {
+                    (Value::Duration(d), Some(format)) => format.format(d),
+                    (Value::Duration(d), None) => format_duration(d),
+                    (Value::Bool(b), _) => if b { "on" } else { "off" }.to_string(),
+                    (Value::Loop(status), _) => format!("{:?}", status),
+                    (Value::Text(text), _) => text,
+                };
+                for filter in filters {
+                    text = apply_filter(filter, text);
+                }
+                out.push_str(&text);
+            }
+            Node::If { var, negate, then, otherwise } => {
+                let branch = if lookup(var).is_truthy() != *negate { then } else { otherwise };
+                render_nodes(branch, lookup, out);
+            }
+        }
+    }
+}
+
+fn apply_filter(filter: &Filter, text: String) -> String {
+    match filter {
+        Filter::Upper => text.to_uppercase(),
+        Filter::Lower => text.to_lowercase(),
+        Filter::Truncate(max) => {
+            if text.width() <= *max {
+                text
+            } else if *max == 0 {
+                String::new()
+            } else {
+                let mut cut = String::new();
+                let mut width = 0;
+                for c in text.chars() {
+                    width += c.width().unwrap_or(0);
+                    if width > max - 1 {
+                        break;
+                    }
+                    cut.push(c);
+                }
+                cut.push('…');
+                cut
+            }
+        }
+        Filter::Pad(width) => {
+            let padding = width.saturating_sub(text.width());
+            text + &" ".repeat(padding)
+        }
+        Filter::Lpad(width) => {
+            let padding = width.saturating_sub(text.width());
+            " ".repeat(padding) + &text
+        }
+        Filter::Default(fallback) if text.is_empty() => fallback.clone(),
+        Filter::Default(_) => text,
+    }
+}
+
+fn value(var: &Var, state: &PlayerState, message: &str) -> Value {
+    let name = match var {
+        Var::Meta(key) => return Value::Text(state.meta(key).unwrap_or_default()),
+        Var::Named(name, _) => *name,
+    };
+
+    let number = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+    let date = state.content_created().unwrap_or("");
+
+    let text = match name {
+        "title" => state.title.clone(),
+        "artists" | "artist" => state.artists.clone(),
+        "album-artist" => state.album_artists().join(", "),
+        "album" => state.album.clone(),
+        "track-number" => number(state.track_number()),
+        "disc-number" => number(state.disc_number()),
+        "genre" => state.genres().join(", "),
+        "composer" => state.composers().join(", "),
+        "date" => date.get(..10).unwrap_or(date).to_string(),
+        "year" => date.get(..4).unwrap_or(date).to_string(),
+        "rating" => state.user_rating().map(rating_stars).unwrap_or_default(),
+        "url" => state.url().unwrap_or("").to_string(),
+        "lyrics" => state.lyrics().unwrap_or("").to_string(),
+        "status-icon" => state.status.icon().to_string(),
+        "status" => format!("{:?}", state.status),
+        "playing" => return Value::Bool(state.status == Status::Playing),
+        "paused" => return Value::Bool(state.status == Status::Paused),
+        "stopped" => return Value::Bool(state.status == Status::Stopped),
+        "position" => return Value::Duration(state.current_position()),
+        "length" => return Value::Duration(state.length),
+        "remaining" => return Value::Duration(state.length.saturating_sub(state.current_position())),
+        "volume" => format!("{}%", (state.volume * 100.0) as u8),
+        "playlist" => state.active_playlist.as_ref().map_or("", |p| p.name.as_str()).to_string(),
+        "player" | "identity" => state.player_name.clone(),
+        "desktop-entry" => state.desktop_entry.clone(),
+        "shuffle" => return Value::Bool(state.shuffle),
+        "loop" => return Value::Loop(state.loop_status),
+        "rate" => format!("{:.2}x", state.rate),
+        "message" => message.to_string(),
+        _ => String::new(),
+    };
+    Value::Text(text)
+}
+
+fn track_value(var: &Var, track: &Track, index: usize) -> Value {
+    let text = match var {
+        Var::Named("index", _) => (index + 1).to_string(),
+        Var::Named("title", _) => track.title.clone(),
+        Var::Named("artists" | "artist", _) => track.artists.clone(),
+        Var::Named("album", _) => track.album.clone(),
+        Var::Named("length", _) => return Value::Duration(track.length),
+        _ => String::new(),
+    };
+    Value::Text(text)
+}
+
+/// Five-star rendering of a 0.0-1.0 rating
+fn rating_stars(rating: f64) -> String {
+    let filled = (rating.clamp(0.0, 1.0) * 5.0).round() as usize;
+    format!("{}{}", "★".repeat(filled), "☆".repeat(5 - filled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mpris::{Metadata, MetadataValue};
+    use std::collections::HashMap;
+
+    fn track() -> Track {
+        Track {
+            id: "/track/0".into(),
+            title: "Song".into(),
+            artists: "Band".into(),
+            album: String::new(),
+            length: Duration::from_secs(125),
+        }
+    }
+
+    fn state() -> PlayerState {
+        let metadata = HashMap::from([
+            ("xesam:genre".to_string(), MetadataValue::Array(vec![MetadataValue::String("Jazz".into())])),
+            ("custom:mood".to_string(), MetadataValue::String("calm".into())),
+        ]);
+        PlayerState {
+            connected: true,
+            title: "Blue in Green".into(),
+            artists: "Miles Davis".into(),
+            position: Duration::from_secs(3725),
+            length: Duration::from_secs(4000),
+            metadata: Metadata::from(metadata),
+            ..PlayerState::default()
+        }
+    }
+
+    fn render(source: &str, state: &PlayerState) -> String {
+        Template::parse(source).unwrap().render(state, "")
+    }
+
+    fn error(source: &str) -> ParseError {
+        Template::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn filters_take_arguments() {
+        let state = state();
+        assert_eq!(render("{title|upper}", &state), "BLUE IN GREEN");
+        assert_eq!(render("{title|truncate(6)|lower}", &state), "blue …");
+        assert_eq!(render("[{artist|pad(12)}]", &state), "[Miles Davis ]");
+        assert_eq!(render("[{artist|lpad(12)}]", &state), "[ Miles Davis]");
+        assert_eq!(render(r#"{album|default("No \"album\"")}"#, &state), "No \"album\"");
+
+        // Widths are terminal columns, so wide characters count double
+        let wide = PlayerState {
+            title: "青い影".into(),
+            ..state.clone()
+        };
+        assert_eq!(render("[{title|pad(8)}]", &wide), "[青い影  ]");
+        assert_eq!(render("[{title|lpad(8)}]", &wide), "[  青い影]");
+        assert_eq!(render("{title|truncate(5)}", &wide), "青い…");
+        assert_eq!(render("{title|truncate(4)}", &wide), "青…");
+        assert_eq!(render("{title|truncate(6)}", &wide), "青い影");
+        assert_eq!(render("{title|default(\"-\")}", &state), "Blue in Green");
+    }
+
+    #[test]
+    fn braces_escape_by_doubling() {
+        assert_eq!(render("{{{title}}} costs $5", &state()), "{Blue in Green} costs $5");
+    }
+
+    #[test]
+    fn conditionals_nest() {
+        let mut state = state();
+        let source = "{if title}{if !album}no album{else}{album}{end}{else}nothing{end}";
+        assert_eq!(render(source, &state), "no album");
+
+        state.album = "Kind of Blue".into();
+        assert_eq!(render(source, &state), "Kind of Blue");
+
+        state.title.clear();
+        assert_eq!(render(source, &state), "nothing");
+    }
+
+    #[test]
+    fn loop_is_false_when_off() {
+        let mut state = state();
+        let source = "{if loop}looping {loop}{else}once{end}";
+        assert_eq!(render(source, &state), "once");
+
+        state.loop_status = Loop::Track;
+        assert_eq!(render(source, &state), "looping Track");
+    }
+
+    #[test]
+    fn durations_take_formats() {
+        let state = state();
+        assert_eq!(render("{position:h:mm:ss}", &state), "1:02:05");
+        assert_eq!(render("{position:m:ss}", &state), "62:05");
+        assert_eq!(render("{remaining:mm:ss}", &state), "04:35");
+        assert_eq!(render("{length:s}s", &state), "4000s");
+    }
+
+    #[test]
+    fn meta_reads_raw_fields() {
+        let state = state();
+        assert_eq!(render("{meta[xesam:genre]}/{meta[custom:mood]|upper}", &state), "Jazz/CALM");
+        assert_eq!(render("{meta[missing]|default(\"?\")}", &state), "?");
+        assert_eq!(render("$meta[custom:mood]!", &state), "calm!");
+    }
+
+    #[test]
+    fn legacy_variables_take_the_longest_name() {
+        let state = state();
+        assert_eq!(render("$artists|$artist|$titles|$5", &state), "Miles Davis|Miles Davis|Blue in Greens|$5");
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let err = error("{title} {nope}");
+        assert_eq!((err.message.as_str(), err.column), ("unknown variable 'nope'", 9));
+
+        let err = error("{title|truncate(x)}");
+        assert_eq!(err.column, 16);
+
+        assert_eq!(error("{title:mm}").column, 1);
+        assert_eq!(error("{position:xx}").column, 10);
+        assert_eq!(error("ab {if title}x").column, 3);
+        assert_eq!(error("{end}").column, 0);
+        assert_eq!(error("{title").message, "unclosed '{'");
+        assert_eq!(error("a } b").column, 2);
+        assert_eq!(
+            error("{nope}").to_string(),
+            "unknown variable 'nope' at column 2 of template \"{nope}\""
+        );
+    }
+
+    #[test]
+    fn track_templates_use_the_track_scope() {
+        let template = TrackTemplate::parse("{index}. {title|upper} ({length}){if album} on {album}{end}").unwrap();
+        assert_eq!(template.render(&track(), 2), "3. SONG (02:05)");
+
+        let legacy = TrackTemplate::parse("$index. $titles").unwrap();
+        assert_eq!(legacy.render(&track(), 0), "1. Songs");
+
+        assert!(TrackTemplate::parse("{volume}").is_err());
+        assert!(TrackTemplate::parse("{meta[xesam:genre]}").is_err());
+    }
+}
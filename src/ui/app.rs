@@ -1,67 +1,146 @@
-use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind};
 use ratatui::{
     backend::CrosstermBackend,
-    widgets::Paragraph,
+    layout::Rect,
+    widgets::{Clear, Paragraph},
     Frame, Terminal,
 };
-use std::io;
+use std::io::{self, Stdout};
 use std::time::{Duration, Instant};
 
-use crate::config::Config;
-use crate::cover::CoverArtLoader;
-use crate::mpris_client::{MprisClient, PlayerState};
-use super::widgets::{render_layout, WidgetAreas};
+use crate::action::{Action, PlayerTarget};
+use crate::config::{Config, ScrollAction, ToastPosition};
+use crate::control::{ControlServer, Request, Response};
+use crate::log;
+use crate::backend::{self, PlayerBackend};
+use crate::mpris_client::{PlayerState, Unsupported, RATE_STEP};
+use super::menu::ContextMenu;
+use super::open::{OpenInput, OpenMode, OpenPrompt};
+use super::picker::PlayerPicker;
+use super::playlists::PlaylistBrowser;
+use super::popup::PopupInput;
+use super::terminal::{self, Signals};
+use super::widgets::{build_style, render_layout, HitMap, WidgetKind, WidgetStates};
+
+/// Mouse drag in progress
+#[derive(Clone, Copy)]
+enum Drag {
+    /// Scrubbing a progress bar; the player only seeks on release
+    Seek { area: Rect, position: Duration },
+    /// Dragging a volume bar, which the volume follows live
+    Volume { area: Rect, level: f64 },
+}
 
 /// Main application
 pub struct App {
     config: Config,
-    mpris: MprisClient,
+    backend: Box<dyn PlayerBackend>,
     state: PlayerState,
-    cover_loader: CoverArtLoader,
     running: bool,
-    widget_areas: WidgetAreas,
+    /// Interactive widgets from the last frame
+    hits: HitMap,
+    widget_states: WidgetStates,
+    picker: Option<PlayerPicker>,
+    playlist_browser: Option<PlaylistBrowser>,
+    open_prompt: Option<OpenPrompt>,
+    context_menu: Option<ContextMenu>,
+    drag: Option<Drag>,
+    /// Toast message and when it was shown
+    message: Option<(String, Instant)>,
+    /// Suspend (Ctrl+Z) was pressed; handled by the main loop
+    suspend_requested: bool,
+    /// Active entry of `Config.layouts`; None for the default layout
+    layout: Option<String>,
+    /// Socket for `mplay ctl`, opened by `run`
+    control: Option<ControlServer>,
 }
 
 impl App {
-    /// Create a new App
+    /// Create a new App talking to the configured backend
     pub fn new(config: Config) -> Self {
-        let mpris = MprisClient::new(config.players.clone());
+        let backend = backend::from_config(&config, config.mpris_server);
+        Self::with_backend(config, backend)
+    }
 
+    /// Create a new App driving the given backend
+    pub fn with_backend(config: Config, backend: Box<dyn PlayerBackend>) -> Self {
         Self {
             config,
-            mpris,
+            backend,
             state: PlayerState::default(),
-            cover_loader: CoverArtLoader::new(),
             running: true,
-            widget_areas: WidgetAreas::default(),
+            hits: HitMap::default(),
+            widget_states: WidgetStates::new(),
+            picker: None,
+            playlist_browser: None,
+            open_prompt: None,
+            context_menu: None,
+            drag: None,
+            message: None,
+            suspend_requested: false,
+            layout: None,
+            control: None,
         }
     }
 
     /// Run the application
     pub fn run(&mut self) -> Result<()> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+        // Setup terminal, making sure every exit path undoes it
+        terminal::install_panic_hook();
+        let signals = Signals::register()?;
+
+        // Scripts can't reach this instance without it, but the UI still works
+        self.control = ControlServer::bind().map_err(|e| log::error(&e)).ok();
+
+        terminal::setup()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let result = Terminal::new(backend)
+            .map_err(Into::into)
+            .and_then(|mut terminal| self.main_loop(&mut terminal, &signals));
+
+        // Restore terminal
+        terminal::restore()?;
+
+        result
+    }
 
+    /// Event loop, run between terminal setup and teardown
+    fn main_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        signals: &Signals,
+    ) -> Result<()> {
         // Initial connection
-        self.mpris.connect().ok();
-        self.state = self.mpris.get_state();
+        self.backend.connect().ok();
+        self.state = self.backend.get_state();
 
         // Main loop
         let tick_rate = Duration::from_millis(100);
         let state_update_rate = Duration::from_millis(500);
+        let position_resync_rate = Duration::from_secs(5);
         let mut last_tick = Instant::now();
         let mut last_state_update = Instant::now();
 
         while self.running {
+            if signals.quit_requested() {
+                break;
+            }
+
+            // Ctrl+Z or an external SIGTSTP: hand the terminal back and stop
+            if self.suspend_requested || signals.take_suspend() {
+                self.suspend_requested = false;
+                terminal::suspend()?;
+                signals.take_resumed();
+                terminal.clear()?;
+            }
+
+            // Stopped by something we couldn't intercept (SIGSTOP)
+            if signals.take_resumed() {
+                terminal::setup()?;
+                terminal.clear()?;
+            }
+
             // Draw UI
             terminal.draw(|f| self.ui(f))?;
 
@@ -73,125 +152,680 @@ impl App {
             if event::poll(timeout)? {
                 match event::read()? {
                     Event::Key(key) => {
-                        self.handle_key(key.code, key.modifiers)?;
+                        let result = self.handle_key(key.code, key.modifiers);
+                        self.report(result);
                     }
                     Event::Mouse(mouse) => {
-                        self.handle_mouse(mouse.kind, mouse.column, mouse.row)?;
+                        let result = self.handle_mouse(mouse.kind, mouse.column, mouse.row);
+                        self.report(result);
                     }
                     _ => {}
                 }
             }
 
-            // Update player state less frequently
-            if last_state_update.elapsed() >= state_update_rate {
-                self.state = self.mpris.get_state();
+            self.apply_events();
+            self.process_control();
+
+            // Poll players that don't emit signals; for signalling players the
+            // position is interpolated and only occasionally re-sampled
+            if self.backend.emits_signals() {
+                if last_state_update.elapsed() >= position_resync_rate {
+                    self.state = self.backend.refresh_position();
+                    last_state_update = Instant::now();
+                }
+            } else if last_state_update.elapsed() >= state_update_rate {
+                self.state = self.backend.get_state();
                 last_state_update = Instant::now();
             }
 
             last_tick = Instant::now();
         }
 
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
+        Ok(())
+    }
+
+    /// Apply change notifications as they arrive
+    fn apply_events(&mut self) {
+        if self.backend.process_events() {
+            self.state = self.backend.state().clone();
+        }
+    }
+
+    /// Answer requests from `mplay ctl`
+    fn process_control(&mut self) {
+        if let Some(mut control) = self.control.take() {
+            control.process(|request| self.handle_request(request));
+            self.control = Some(control);
+        }
+    }
 
+    fn handle_request(&mut self, request: Request) -> Response {
+        let result = match request {
+            Request::Player { name } => self.run_action(&Action::SwitchPlayer(PlayerTarget::Named(name))),
+            Request::Toast { text } => {
+                self.notify(text);
+                Ok(())
+            }
+            Request::Layout { name } => self.set_layout(&name),
+            Request::Action { action } => self.run_action(&action),
+            Request::Status => return Response::state(self.status_json()),
+        };
+
+        match result {
+            Ok(()) => Response::ok(),
+            Err(e) => Response::error(format!("{:#}", e)),
+        }
+    }
+
+    /// Switch to a layout from `Config.layouts`, or back to `default`
+    fn set_layout(&mut self, name: &str) -> Result<()> {
+        if name == "default" {
+            self.layout = None;
+        } else if self.config.layouts.contains_key(name) {
+            self.layout = Some(name.to_string());
+        } else {
+            let mut names: Vec<&str> = self.config.layouts.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            names.insert(0, "default");
+            anyhow::bail!("Unknown layout '{}' (available: {})", name, names.join(", "));
+        }
         Ok(())
     }
 
+    /// Player state as reported to `mplay ctl status`
+    fn status_json(&self) -> serde_json::Value {
+        let state = &self.state;
+        serde_json::json!({
+            "connected": state.connected,
+            "player": state.player_name,
+            "status": format!("{:?}", state.status),
+            "title": state.title,
+            "artist": state.artists,
+            "album": state.album,
+            "position": state.current_position().as_secs_f64(),
+            "length": state.length.as_secs_f64(),
+            "volume": state.volume,
+            "shuffle": state.shuffle,
+            "loop": format!("{:?}", state.loop_status),
+            "layout": self.layout.as_deref().unwrap_or("default"),
+        })
+    }
+
     /// Render the UI
     fn ui(&mut self, frame: &mut Frame) {
         let area = frame.area();
 
         let inner_area = area;
 
+        let expired = self.message.as_ref().is_some_and(|(_, shown)| {
+            shown.elapsed() >= Duration::from_millis(self.config.toast.timeout_ms)
+        });
+        if expired {
+            self.message = None;
+        }
+
         if !self.state.connected {
+            self.hits = HitMap::default();
             let msg = Paragraph::new("No MPRIS-compatible player found.\nStart a media player and press 'r' to reconnect.");
             frame.render_widget(msg, inner_area);
-            return;
+        } else {
+            // Show where a seek would land while the progress bar is dragged
+            let preview;
+            let state = match self.drag {
+                Some(Drag::Seek { position, .. }) => {
+                    preview = PlayerState {
+                        position,
+                        position_sampled_at: None,
+                        ..self.state.clone()
+                    };
+                    &preview
+                }
+                _ => &self.state,
+            };
+
+            // Render the configured layout and track widget areas
+            self.hits = render_layout(
+                frame,
+                inner_area,
+                self.layout
+                    .as_ref()
+                    .and_then(|name| self.config.layouts.get(name))
+                    .unwrap_or(&self.config.layout),
+                &self.config.widgets,
+                state,
+                self.message.as_ref().map_or("", |(text, _)| text.as_str()),
+                &mut self.widget_states,
+            );
+        }
+
+        self.render_toast(frame, area);
+
+        if let Some(ref mut picker) = self.picker {
+            picker.render(
+                frame,
+                area,
+                self.backend.players(),
+                self.backend.active_bus_name(),
+                self.backend.is_pinned(),
+            );
+        }
+
+        if let Some(ref mut browser) = self.playlist_browser {
+            browser.render(
+                frame,
+                area,
+                self.backend.playlists(),
+                self.state.active_playlist.as_ref(),
+                &self.config.playlists.icon,
+            );
         }
 
-        // Render the configured layout and track widget areas
-        self.widget_areas = render_layout(
-            frame,
-            inner_area,
-            &self.config.layout,
-            &self.config.widgets,
-            &self.state,
-            &mut self.cover_loader,
+        if let Some(ref mut prompt) = self.open_prompt {
+            prompt.render(frame, area);
+        }
+
+        if let Some(ref mut menu) = self.context_menu {
+            menu.render(frame, area);
+        }
+    }
+
+    /// Render the toast row, if a message is showing
+    fn render_toast(&self, frame: &mut Frame, area: Rect) {
+        let Some((ref text, _)) = self.message else {
+            return;
+        };
+
+        let toast = &self.config.toast;
+        let y = match toast.position {
+            ToastPosition::Top => area.y,
+            ToastPosition::Bottom => area.y + area.height.saturating_sub(1),
+            ToastPosition::Hidden => return,
+        };
+        let row = Rect { y, height: area.height.min(1), ..area };
+
+        frame.render_widget(Clear, row);
+        frame.render_widget(
+            Paragraph::new(text.as_str())
+                .alignment(toast.align.into())
+                .style(build_style(&toast.style)),
+            row,
         );
     }
 
+    /// Show a toast message
+    fn notify(&mut self, text: impl Into<String>) {
+        self.message = Some((text.into(), Instant::now()));
+    }
+
+    /// Show errors as a toast instead of tearing down the UI
+    fn report(&mut self, result: Result<()>) {
+        if let Err(err) = result {
+            if err.downcast_ref::<Unsupported>().is_some() {
+                self.notify(err.to_string());
+            } else {
+                log::error(&err);
+                self.notify(format!("Error: {:#}", err));
+            }
+        }
+    }
+
     /// Handle mouse events
     fn handle_mouse(&mut self, kind: MouseEventKind, col: u16, row: u16) -> Result<()> {
-        if let MouseEventKind::Down(MouseButton::Left) = kind {
-            // Check if click is in the controls area - toggle play/pause
-            if let Some(controls_area) = self.widget_areas.controls {
-                if col >= controls_area.x
-                    && col < controls_area.x + controls_area.width
-                    && row >= controls_area.y
-                    && row < controls_area.y + controls_area.height
-                {
-                    self.mpris.toggle()?;
-                    self.state = self.mpris.get_state();
+        if self.picker.is_some() {
+            return self.handle_picker_mouse(kind, col, row);
+        }
+        if self.playlist_browser.is_some() {
+            return self.handle_playlist_mouse(kind, col, row);
+        }
+        if let Some(ref mut prompt) = self.open_prompt {
+            let len = prompt.len();
+            let input = match prompt.popup.handle_mouse(kind, col, row, len) {
+                PopupInput::Consumed => OpenInput::Consumed,
+                PopupInput::Chosen(index) => prompt.choose(index),
+                PopupInput::Closed => OpenInput::Closed,
+            };
+            return self.apply_open_input(input);
+        }
+
+        if self.context_menu.is_some() {
+            return self.handle_menu_mouse(kind, col, row);
+        }
+
+        self.widget_states.pointer = Some((col, row));
+
+        // A drag follows the mouse wherever it goes until the button is released
+        if let Some(drag) = self.drag.take() {
+            return self.continue_drag(drag, kind, col);
+        }
+
+        let hit = self.hits.at(col, row).cloned();
+
+        // Buttons run their action when released over the same button
+        if let MouseEventKind::Up(MouseButton::Left) = kind {
+            if let Some(pressed) = self.widget_states.pressed.take() {
+                if let WidgetKind::Button(ref action) = pressed.kind {
+                    if hit.as_ref() == Some(&pressed) {
+                        return self.run_action(action);
+                    }
                 }
             }
+            return Ok(());
+        }
 
-            // Check if click is on progress bar for seeking
-            if let Some(progress_area) = self.widget_areas.progress {
-                if progress_area.width > 0
-                    && col >= progress_area.x
-                    && col < progress_area.x + progress_area.width
-                    && row >= progress_area.y
-                    && row < progress_area.y + progress_area.height
-                {
-                    let rel_col = col.saturating_sub(progress_area.x);
-                    let ratio = rel_col as f64 / progress_area.width as f64;
-                    let new_pos = Duration::from_secs_f64(self.state.length.as_secs_f64() * ratio);
-                    self.mpris.set_position(new_pos)?;
-                    self.state = self.mpris.get_state();
+        if let MouseEventKind::Down(MouseButton::Right) = kind {
+            if !self.config.mouse.menu.is_empty() {
+                self.context_menu = Some(ContextMenu::new(&self.config.mouse.menu, (col, row)));
+            }
+            return Ok(());
+        }
+
+        let scroll_up = match kind {
+            MouseEventKind::ScrollUp => Some(true),
+            MouseEventKind::ScrollDown => Some(false),
+            _ => None,
+        };
+        if let Some(up) = scroll_up {
+            return match hit.map(|hit| hit.kind) {
+                Some(WidgetKind::Queue) => {
+                    self.move_queue_selection(if up { -1 } else { 1 });
+                    Ok(())
+                }
+                Some(WidgetKind::Progress) => self.scroll(ScrollAction::Seek, up),
+                Some(WidgetKind::Volume) => self.scroll(ScrollAction::Volume, up),
+                _ => self.scroll(self.config.mouse.scroll, up),
+            };
+        }
+
+        let (Some(hit), MouseEventKind::Down(MouseButton::Left)) = (hit, kind) else {
+            return Ok(());
+        };
+        let area = hit.area;
+
+        match hit.kind {
+            WidgetKind::Button(_) => self.widget_states.pressed = Some(hit),
+            WidgetKind::Progress => {
+                let position = self.seek_target(area, col);
+                self.drag = Some(Drag::Seek { area, position });
+            }
+            WidgetKind::Volume => {
+                let level = volume_at(area, col);
+                self.drag = Some(Drag::Volume { area, level });
+                self.set_volume(level)?;
+            }
+            WidgetKind::Queue => {
+                let index = self.widget_states.queue.offset() + (row - area.y) as usize;
+                if index < self.state.tracks.len() {
+                    self.widget_states.queue.select(Some(index));
+                    self.backend.go_to(index)?;
+                    self.refresh_state();
                 }
             }
         }
         Ok(())
     }
 
+    /// Follow the mouse during a drag, finishing it on release
+    fn continue_drag(&mut self, drag: Drag, kind: MouseEventKind, col: u16) -> Result<()> {
+        match (drag, kind) {
+            (Drag::Seek { area, .. }, MouseEventKind::Drag(MouseButton::Left)) => {
+                let position = self.seek_target(area, col);
+                self.drag = Some(Drag::Seek { area, position });
+            }
+            (Drag::Seek { position, .. }, MouseEventKind::Up(MouseButton::Left)) => {
+                self.backend.set_position(position)?;
+                self.refresh_state();
+            }
+            (Drag::Volume { area, level }, MouseEventKind::Drag(MouseButton::Left)) => {
+                // Compare with the last level set, as signals may not have caught up
+                let target = volume_at(area, col);
+                self.drag = Some(Drag::Volume { area, level: target });
+                if target != level {
+                    self.set_volume(target)?;
+                }
+            }
+            (Drag::Volume { .. }, MouseEventKind::Up(MouseButton::Left)) => {}
+            _ => self.drag = Some(drag),
+        }
+        Ok(())
+    }
+
+    /// Track position under a column of a progress bar
+    fn seek_target(&self, area: Rect, col: u16) -> Duration {
+        Duration::from_secs_f64(self.state.length.as_secs_f64() * bar_ratio(area, col))
+    }
+
+    fn set_volume(&mut self, level: f64) -> Result<()> {
+        self.backend.set_volume(level)?;
+        self.refresh_state();
+        Ok(())
+    }
+
+    /// One scroll wheel step
+    fn scroll(&mut self, target: ScrollAction, up: bool) -> Result<()> {
+        let mouse = &self.config.mouse;
+        let sign = if up { 1.0 } else { -1.0 };
+        let action = match target {
+            ScrollAction::Volume => Action::Volume(sign * f64::from(mouse.volume_step_percent)),
+            ScrollAction::Seek => Action::Seek(sign * mouse.seek_step_secs as f64),
+            ScrollAction::None => return Ok(()),
+        };
+        self.run_action(&action)
+    }
+
+    /// Handle mouse events while the context menu is open
+    fn handle_menu_mouse(&mut self, kind: MouseEventKind, col: u16, row: u16) -> Result<()> {
+        let Some(ref mut menu) = self.context_menu else {
+            return Ok(());
+        };
+
+        // Right-clicking elsewhere moves the menu there
+        if let MouseEventKind::Down(MouseButton::Right) = kind {
+            if !menu.popup.contains(col, row) {
+                self.context_menu = Some(ContextMenu::new(&self.config.mouse.menu, (col, row)));
+            }
+            return Ok(());
+        }
+
+        let len = menu.len();
+        let input = menu.popup.handle_mouse(kind, col, row, len);
+        self.apply_menu_input(input)
+    }
+
+    /// Act on the result of input routed to the context menu
+    fn apply_menu_input(&mut self, input: PopupInput) -> Result<()> {
+        match input {
+            PopupInput::Consumed => Ok(()),
+            PopupInput::Chosen(index) => {
+                let menu = self.context_menu.take();
+                match menu.as_ref().and_then(|menu| menu.action(index)) {
+                    Some(action) => self.run_action(action),
+                    None => Ok(()),
+                }
+            }
+            PopupInput::Closed => {
+                self.context_menu = None;
+                Ok(())
+            }
+        }
+    }
+
     /// Handle key press
     fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<()> {
         let key_str = key_to_string(code, modifiers);
 
+        if self.picker.is_some() {
+            return self.handle_picker_key(code, &key_str);
+        }
+        if self.playlist_browser.is_some() {
+            return self.handle_playlist_key(code, &key_str);
+        }
+        if let Some(ref mut prompt) = self.open_prompt {
+            let input = prompt.handle_key(code);
+            return self.apply_open_input(input);
+        }
+        if let Some(ref mut menu) = self.context_menu {
+            let len = menu.len();
+            let input = menu.popup.handle_key(code, len);
+            return self.apply_menu_input(input);
+        }
+
         // Check keybindings
-        let keybinds = &self.config.keybinds;
-
-        if keybinds.quit.iter().any(|k| k == &key_str) {
-            self.running = false;
-        } else if keybinds.toggle.iter().any(|k| k == &key_str) {
-            self.mpris.toggle()?;
-        } else if keybinds.next.iter().any(|k| k == &key_str) {
-            self.mpris.next()?;
-        } else if keybinds.prev.iter().any(|k| k == &key_str) {
-            self.mpris.prev()?;
-        } else if keybinds.seek_forward.iter().any(|k| k == &key_str) {
-            self.mpris.seek_forward(Duration::from_secs(5))?;
-        } else if keybinds.seek_backward.iter().any(|k| k == &key_str) {
-            self.mpris.seek_backward(Duration::from_secs(5))?;
-        } else if keybinds.volume_up.iter().any(|k| k == &key_str) {
-            self.mpris.adjust_volume(0.05)?;
-        } else if keybinds.volume_down.iter().any(|k| k == &key_str) {
-            self.mpris.adjust_volume(-0.05)?;
-        } else if let KeyCode::Char('r') = code {
-            self.mpris.connect()?;
+        if let Some(action) = self.config.keybinds.get(&key_str).cloned() {
+            return self.run_action(&action);
         }
 
         // Update state after action
-        self.state = self.mpris.get_state();
+        self.refresh_state();
+
+        Ok(())
+    }
+
+    /// Run an action from a key, button, menu or `mplay ctl`
+    fn run_action(&mut self, action: &Action) -> Result<()> {
+        match action {
+            Action::Quit => self.running = false,
+            Action::Suspend => {
+                self.suspend_requested = true;
+                return Ok(());
+            }
+            Action::Toggle => self.backend.toggle()?,
+            Action::Next => self.backend.next()?,
+            Action::Prev => self.backend.prev()?,
+            Action::Seek(secs) => {
+                let offset = Duration::try_from_secs_f64(secs.abs())
+                    .map_err(|_| anyhow!("Seek offset too large"))?;
+                if *secs < 0.0 {
+                    self.backend.seek_backward(offset)?;
+                } else {
+                    self.backend.seek_forward(offset)?;
+                }
+            }
+            Action::SeekPercent(percent) => {
+                self.backend.set_position(self.state.length.mul_f64(percent / 100.0))?;
+            }
+            Action::Volume(percent) => self.backend.adjust_volume(percent / 100.0)?,
+            Action::SetVolume(percent) => self.backend.set_volume(percent / 100.0)?,
+            Action::ToggleShuffle => self.backend.toggle_shuffle()?,
+            Action::CycleLoop => self.backend.cycle_loop()?,
+            Action::RateUp => self.backend.adjust_rate(RATE_STEP)?,
+            Action::RateDown => self.backend.adjust_rate(-RATE_STEP)?,
+            Action::RateReset => self.backend.reset_rate()?,
+            Action::Raise => self.backend.raise()?,
+            Action::QuitPlayer => self.backend.quit_player()?,
+            Action::ToggleFullscreen => self.backend.toggle_fullscreen()?,
+            Action::QueueUp => {
+                self.move_queue_selection(-1);
+                return Ok(());
+            }
+            Action::QueueDown => {
+                self.move_queue_selection(1);
+                return Ok(());
+            }
+            Action::QueuePlay => {
+                if let Some(index) = self.widget_states.queue.selected() {
+                    self.backend.go_to(index)?;
+                }
+            }
+            Action::QueueRemove => {
+                if let Some(index) = self.widget_states.queue.selected() {
+                    self.backend.remove_track(index)?;
+                }
+            }
+            Action::SwitchPlayer(target) => {
+                if let PlayerTarget::Named(name) = target {
+                    if !self.backend.select_player(name)? {
+                        bail!("No player matching '{}'", name);
+                    }
+                } else {
+                    self.backend.cycle_player(*target == PlayerTarget::Next)?;
+                }
+                self.state = self.backend.state().clone();
+                return Ok(());
+            }
+            Action::PlayerPicker => {
+                self.backend.refresh_players()?;
+                self.picker = Some(PlayerPicker::new(self.backend.players(), self.backend.active_bus_name()));
+                return Ok(());
+            }
+            Action::PlaylistBrowser => {
+                let playlists = &self.config.playlists;
+                self.backend.refresh_playlists(playlists.order, playlists.reverse, playlists.max_count)?;
+                self.playlist_browser = Some(PlaylistBrowser::new(
+                    self.backend.playlists(),
+                    self.state.active_playlist.as_ref(),
+                ));
+                return Ok(());
+            }
+            Action::OpenUri => {
+                self.open_prompt(OpenMode::Uri);
+                return Ok(());
+            }
+            Action::OpenFile => {
+                self.open_prompt(OpenMode::Files);
+                return Ok(());
+            }
+            Action::PinPlayer => {
+                let text = if self.backend.toggle_pin() {
+                    format!("Pinned to {}", self.state.player_name)
+                } else {
+                    "Unpinned".to_string()
+                };
+                self.notify(text);
+                return Ok(());
+            }
+            Action::Reconnect => {
+                self.backend.connect()?;
+                // A new player has not signalled anything yet
+                self.state = self.backend.get_state();
+                return Ok(());
+            }
+        }
+
+        // Update state after action
+        self.refresh_state();
+
+        Ok(())
+    }
 
+    /// Move the queue highlight, clamped to the track list
+    fn move_queue_selection(&mut self, delta: isize) {
+        let len = self.state.tracks.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.widget_states.queue.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        self.widget_states.queue.select(Some(next));
+    }
+
+    /// Handle key press while the player picker is open
+    fn handle_picker_key(&mut self, code: KeyCode, key_str: &str) -> Result<()> {
+        if self.config.keybinds.get(key_str) == Some(&Action::PlayerPicker) {
+            self.picker = None;
+            return Ok(());
+        }
+
+        let len = self.backend.players().len();
+        let input = match self.picker {
+            Some(ref mut picker) => picker.popup.handle_key(code, len),
+            None => return Ok(()),
+        };
+        self.apply_picker_input(input)
+    }
+
+    /// Handle mouse events while the player picker is open
+    fn handle_picker_mouse(&mut self, kind: MouseEventKind, col: u16, row: u16) -> Result<()> {
+        let len = self.backend.players().len();
+        let input = match self.picker {
+            Some(ref mut picker) => picker.popup.handle_mouse(kind, col, row, len),
+            None => return Ok(()),
+        };
+        self.apply_picker_input(input)
+    }
+
+    /// Act on the result of input routed to the player picker
+    fn apply_picker_input(&mut self, input: PopupInput) -> Result<()> {
+        match input {
+            PopupInput::Consumed => Ok(()),
+            PopupInput::Chosen(index) => self.pick_player(index),
+            PopupInput::Closed => {
+                self.picker = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Switch to a picker entry and close the picker
+    fn pick_player(&mut self, index: usize) -> Result<()> {
+        self.picker = None;
+
+        let bus_name = self.backend.players().get(index).map(|p| p.bus_name.clone());
+
+        if let Some(bus_name) = bus_name {
+            self.backend.select_player(&bus_name)?;
+            self.state = self.backend.state().clone();
+        }
+        Ok(())
+    }
+
+    /// Handle key press while the playlist browser is open
+    fn handle_playlist_key(&mut self, code: KeyCode, key_str: &str) -> Result<()> {
+        if self.config.keybinds.get(key_str) == Some(&Action::PlaylistBrowser) {
+            self.playlist_browser = None;
+            return Ok(());
+        }
+
+        let len = self.backend.playlists().len();
+        let input = match self.playlist_browser {
+            Some(ref mut browser) => browser.popup.handle_key(code, len),
+            None => return Ok(()),
+        };
+        self.apply_playlist_input(input)
+    }
+
+    /// Handle mouse events while the playlist browser is open
+    fn handle_playlist_mouse(&mut self, kind: MouseEventKind, col: u16, row: u16) -> Result<()> {
+        let len = self.backend.playlists().len();
+        let input = match self.playlist_browser {
+            Some(ref mut browser) => browser.popup.handle_mouse(kind, col, row, len),
+            None => return Ok(()),
+        };
+        self.apply_playlist_input(input)
+    }
+
+    /// Act on the result of input routed to the playlist browser
+    fn apply_playlist_input(&mut self, input: PopupInput) -> Result<()> {
+        match input {
+            PopupInput::Consumed => {}
+            PopupInput::Chosen(index) => {
+                self.playlist_browser = None;
+                self.backend.activate_playlist(index)?;
+                self.refresh_state();
+            }
+            PopupInput::Closed => self.playlist_browser = None,
+        }
         Ok(())
     }
+
+    /// Show the open prompt with what the player says it can open
+    fn open_prompt(&mut self, mode: OpenMode) {
+        self.open_prompt = Some(OpenPrompt::new(
+            mode,
+            self.backend.supported_uri_schemes(),
+            self.backend.supported_mime_types(),
+        ));
+    }
+
+    /// Act on the result of input routed to the open prompt
+    fn apply_open_input(&mut self, input: OpenInput) -> Result<()> {
+        match input {
+            OpenInput::Consumed => {}
+            OpenInput::Open(uri) => {
+                self.open_prompt = None;
+                self.backend.open_uri(&uri)?;
+                self.refresh_state();
+            }
+            OpenInput::Closed => self.open_prompt = None,
+        }
+        Ok(())
+    }
+
+    /// Re-read state after an action, unless the player will signal the change itself
+    fn refresh_state(&mut self) {
+        if !self.backend.emits_signals() {
+            self.state = self.backend.get_state();
+        }
+    }
+}
+
+/// Position of a column along a bar, from 0.0 at its left edge to 1.0
+fn bar_ratio(area: Rect, col: u16) -> f64 {
+    (col.saturating_sub(area.x) as f64 / area.width.max(1) as f64).clamp(0.0, 1.0)
+}
+
+/// Volume that leaves the cell under a column of a volume bar as the last one filled
+fn volume_at(area: Rect, col: u16) -> f64 {
+    bar_ratio(area, col.saturating_add(1))
 }
 
 /// Convert key event to string representation
@@ -205,6 +839,7 @@ fn key_to_string(code: KeyCode, modifiers: KeyModifiers) -> std::borrow::Cow<'st
         KeyCode::Enter => Cow::Borrowed("Enter"),
         KeyCode::Esc => Cow::Borrowed("Escape"),
         KeyCode::Tab => Cow::Borrowed("Tab"),
+        KeyCode::BackTab => Cow::Borrowed("BackTab"),
         KeyCode::Backspace => Cow::Borrowed("Backspace"),
         KeyCode::Delete => Cow::Borrowed("Delete"),
         KeyCode::Left => Cow::Borrowed("Left"),
@@ -246,3 +881,393 @@ fn key_to_string(code: KeyCode, modifiers: KeyModifiers) -> std::borrow::Cow<'st
     result.push_str(&key_name);
     Cow::Owned(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{Call, MockBackend, MockHandle};
+    use crate::mpris_client::{PlayerEvent, Status};
+    use mpris::MetadataValue;
+    use std::collections::HashMap;
+    use ratatui::backend::TestBackend;
+
+    fn app_with_state(state: PlayerState) -> (App, MockHandle) {
+        app_with_config(Config::default(), state)
+    }
+
+    fn app_with_config(config: Config, state: PlayerState) -> (App, MockHandle) {
+        let (backend, handle) = MockBackend::new(state);
+        let mut app = App::with_backend(config, Box::new(backend));
+        app.state = app.backend.get_state();
+        (app, handle)
+    }
+
+    fn app(title: &str) -> (App, MockHandle) {
+        app_with_state(MockBackend::track_state(title))
+    }
+
+    /// Draw one frame and return the screen as lines of text
+    fn render(app: &mut App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|f| app.ui(f)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn screen_contains(app: &mut App, text: &str) -> bool {
+        render(app).iter().any(|line| line.contains(text))
+    }
+
+    /// Area of the first rendered widget of this kind
+    fn hit_area(app: &App, kind: WidgetKind) -> Rect {
+        let hit = app.hits.hits.iter().find(|hit| hit.kind == kind);
+        hit.unwrap_or_else(|| panic!("no {:?} rendered", kind)).area
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        let result = app.handle_key(code, KeyModifiers::NONE);
+        app.report(result);
+    }
+
+    #[test]
+    fn renders_track_info() {
+        let (mut app, _) = app("Test Title");
+        assert!(screen_contains(&mut app, "Test Title"));
+        assert!(screen_contains(&mut app, "Mock Artist"));
+        assert!(screen_contains(&mut app, "03:00"));
+    }
+
+    #[test]
+    fn space_toggles_playback() {
+        let (mut app, handle) = app("Test Title");
+        press(&mut app, KeyCode::Char(' '));
+
+        assert_eq!(handle.calls(), vec![Call::Toggle]);
+        assert_eq!(app.state.status, Status::Playing);
+    }
+
+    #[test]
+    fn seek_and_volume_keys() {
+        let (mut app, handle) = app("Test Title");
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('k'));
+
+        assert_eq!(
+            handle.calls(),
+            vec![Call::SeekForward(Duration::from_secs(5)), Call::AdjustVolume(0.05)]
+        );
+        assert_eq!(app.state.position, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn configured_keybinds_take_arguments() {
+        let config = Config {
+            keybinds: json5::from_str(r#"{ "x": "seek(+30s)", "%": "seek_percent(50)", "v": "set_volume(80%)" }"#).unwrap(),
+            ..Default::default()
+        };
+        let (mut app, handle) = app_with_config(config, MockBackend::track_state("Test Title"));
+
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('%'));
+        press(&mut app, KeyCode::Char('v'));
+
+        assert_eq!(
+            handle.calls(),
+            vec![
+                Call::SeekForward(Duration::from_secs(30)),
+                Call::SetPosition(Duration::from_secs(90)),
+                Call::SetVolume(0.8),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_action_shows_toast() {
+        let mut state = MockBackend::track_state("Test Title");
+        state.caps.can_go_next = false;
+        let (mut app, handle) = app_with_state(state);
+
+        press(&mut app, KeyCode::Char('n'));
+
+        assert!(handle.calls().is_empty());
+        assert!(screen_contains(&mut app, "Next track not supported by Mock"));
+    }
+
+    #[test]
+    fn click_on_progress_bar_seeks() {
+        let (mut app, handle) = app("Test Title");
+        render(&mut app);
+
+        let area = hit_area(&app, WidgetKind::Progress);
+        let col = area.x + area.width / 2;
+        app.handle_mouse(MouseEventKind::Down(MouseButton::Left), col, area.y).unwrap();
+        app.handle_mouse(MouseEventKind::Up(MouseButton::Left), col, area.y).unwrap();
+
+        let calls = handle.calls();
+        let [Call::SetPosition(position)] = calls.as_slice() else {
+            panic!("expected a seek, got {:?}", calls);
+        };
+        let expected = 180.0 * (col - area.x) as f64 / area.width as f64;
+        assert!((position.as_secs_f64() - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn click_on_button_runs_its_action() {
+        use crate::config::{ButtonConfig, Direction, Layout, LayoutChild, WidgetConfig};
+
+        let mut config = Config::default();
+        config.widgets.insert("skip".into(), WidgetConfig::Button(ButtonConfig {
+            action: Action::Next,
+            text: "Skip".parse().unwrap(),
+            ..Default::default()
+        }));
+        config.layout = Layout {
+            direction: Direction::Vertical,
+            children: vec![LayoutChild::Widget("title".into()), LayoutChild::Widget("skip".into())],
+        };
+        let (mut app, handle) = app_with_config(config, MockBackend::track_state("Test Title"));
+        render(&mut app);
+
+        let area = hit_area(&app, WidgetKind::Button(Action::Next));
+        app.handle_mouse(MouseEventKind::Down(MouseButton::Left), area.x, area.y).unwrap();
+        assert!(handle.calls().is_empty(), "buttons run on release");
+        app.handle_mouse(MouseEventKind::Up(MouseButton::Left), area.x, area.y).unwrap();
+
+        assert_eq!(handle.calls(), vec![Call::Next]);
+    }
+
+    #[test]
+    fn every_progress_bar_seeks_whatever_its_name() {
+        use crate::config::{Direction, Layout, LayoutChild, ProgressConfig, WidgetConfig};
+
+        let mut config = Config::default();
+        for name in ["top", "bottom"] {
+            config.widgets.insert(name.into(), WidgetConfig::Progress(ProgressConfig::default()));
+        }
+        config.layout = Layout {
+            direction: Direction::Vertical,
+            children: vec![LayoutChild::Widget("top".into()), LayoutChild::Widget("bottom".into())],
+        };
+        let (mut app, handle) = app_with_config(config, MockBackend::track_state("Test Title"));
+        render(&mut app);
+
+        let ids: Vec<&str> = app.hits.hits.iter().map(|hit| hit.id.as_str()).collect();
+        assert_eq!(ids, vec!["top", "bottom"]);
+
+        for area in app.hits.hits.clone().iter().map(|hit| hit.area) {
+            app.handle_mouse(MouseEventKind::Down(MouseButton::Left), area.x, area.y).unwrap();
+            app.handle_mouse(MouseEventKind::Up(MouseButton::Left), area.x, area.y).unwrap();
+        }
+        assert_eq!(handle.calls().len(), 2);
+    }
+
+    #[test]
+    fn dragging_progress_bar_previews_and_seeks_on_release() {
+        let (mut app, handle) = app("Test Title");
+        render(&mut app);
+
+        let area = hit_area(&app, WidgetKind::Progress);
+        let end = area.x + area.width - 1;
+        app.handle_mouse(MouseEventKind::Down(MouseButton::Left), area.x, area.y).unwrap();
+        app.handle_mouse(MouseEventKind::Drag(MouseButton::Left), area.x + 2, area.y).unwrap();
+        app.handle_mouse(MouseEventKind::Drag(MouseButton::Left), end, area.y + 3).unwrap();
+
+        let target = app.seek_target(area, end);
+        assert!(handle.calls().is_empty());
+        assert!(screen_contains(&mut app, &crate::mpris_client::format_duration(target)));
+
+        app.handle_mouse(MouseEventKind::Up(MouseButton::Left), 0, 0).unwrap();
+        assert_eq!(handle.calls(), vec![Call::SetPosition(target)]);
+    }
+
+    #[test]
+    fn dragging_volume_bar_sets_absolute_levels() {
+        use crate::config::{Direction, Layout, LayoutChild};
+
+        let config = Config {
+            layout: Layout {
+                direction: Direction::Vertical,
+                children: vec![LayoutChild::Widget("volume".into())],
+            },
+            ..Default::default()
+        };
+        let (mut app, handle) = app_with_config(config, MockBackend::track_state("Test Title"));
+        handle.emit_signals();
+        render(&mut app);
+
+        let area = hit_area(&app, WidgetKind::Volume);
+        let cols = [area.x, area.x + 2, area.x + 4, area.x + 4];
+        app.handle_mouse(MouseEventKind::Down(MouseButton::Left), cols[0], area.y).unwrap();
+        for col in &cols[1..] {
+            app.handle_mouse(MouseEventKind::Drag(MouseButton::Left), *col, area.y).unwrap();
+        }
+        app.handle_mouse(MouseEventKind::Up(MouseButton::Left), cols[3], area.y).unwrap();
+
+        let expected: Vec<Call> = cols[..3].iter().map(|col| Call::SetVolume(volume_at(area, *col))).collect();
+        assert_eq!(handle.calls(), expected);
+        assert_eq!(app.backend.state().volume, volume_at(area, cols[3]));
+    }
+
+    #[test]
+    fn wheel_outside_widgets_changes_volume() {
+        let (mut app, handle) = app("Test Title");
+        render(&mut app);
+
+        app.handle_mouse(MouseEventKind::ScrollUp, 0, 0).unwrap();
+        assert_eq!(handle.calls(), vec![Call::AdjustVolume(0.05)]);
+    }
+
+    #[test]
+    fn huge_wheel_seek_step_shows_toast() {
+        let mut config = Config::default();
+        config.mouse.scroll = ScrollAction::Seek;
+        config.mouse.seek_step_secs = u64::MAX;
+        let (mut app, handle) = app_with_config(config, MockBackend::track_state("Test Title"));
+        render(&mut app);
+
+        let result = app.handle_mouse(MouseEventKind::ScrollUp, 0, 0);
+        app.report(result);
+
+        assert!(handle.calls().is_empty());
+        assert!(screen_contains(&mut app, "Seek offset too large"));
+    }
+
+    #[test]
+    fn right_click_menu_runs_chosen_action() {
+        let (mut app, handle) = app("Test Title");
+        render(&mut app);
+
+        app.handle_mouse(MouseEventKind::Down(MouseButton::Right), 10, 2).unwrap();
+        assert!(screen_contains(&mut app, " Toggle shuffle"));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(handle.calls(), vec![Call::Next]);
+        assert!(app.context_menu.is_none());
+    }
+
+    #[test]
+    fn queue_keys_select_and_play() {
+        let (mut app, handle) = app("Test Title");
+        press(&mut app, KeyCode::Char('J'));
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(handle.calls(), vec![Call::GoTo(1)]);
+        assert_eq!(app.state.title, "Two");
+    }
+
+    #[test]
+    fn scripted_updates_are_rendered() {
+        let (mut app, handle) = app("Test Title");
+        handle.push_update(|state| state.title = "Signalled Title".into());
+
+        app.apply_events();
+
+        assert!(screen_contains(&mut app, "Signalled Title"));
+    }
+
+    /// Track metadata as a player would put in a signal
+    fn signal_metadata(id: &str, title: &str, seconds: i64) -> HashMap<String, MetadataValue> {
+        HashMap::from([
+            ("mpris:trackid".to_string(), MetadataValue::String(id.into())),
+            ("xesam:title".to_string(), MetadataValue::String(title.into())),
+            (
+                "xesam:artist".to_string(),
+                MetadataValue::Array(vec![MetadataValue::String("Signal Artist".into())]),
+            ),
+            ("mpris:length".to_string(), MetadataValue::I64(seconds * 1_000_000)),
+        ])
+    }
+
+    #[test]
+    fn signalled_events_update_the_app() {
+        let (mut app, handle) = app("Test Title");
+        handle.emit_signals();
+
+        handle.push_event(PlayerEvent::PropertiesChanged {
+            changed: HashMap::from([
+                ("PlaybackStatus".to_string(), MetadataValue::String("Playing".into())),
+                ("Metadata".to_string(), MetadataValue::Map(signal_metadata("/track/1", "Two", 240))),
+            ]),
+            invalidated: Vec::new(),
+        });
+        app.apply_events();
+        assert_eq!(app.state.status, Status::Playing);
+        assert_eq!(app.state.track_id.as_deref(), Some("/track/1"));
+        assert!(screen_contains(&mut app, "Signal Artist"));
+        assert!(screen_contains(&mut app, "04:00"));
+
+        handle.push_event(PlayerEvent::Seeked(60_000_000));
+        app.apply_events();
+        assert_eq!(app.state.current_position().as_secs(), 60);
+
+        handle.push_event(PlayerEvent::TrackAdded {
+            metadata: signal_metadata("/track/3", "Four", 200),
+            after: "/track/1".into(),
+        });
+        handle.push_event(PlayerEvent::TrackRemoved("/track/0".into()));
+        app.apply_events();
+        app.apply_events();
+        let ids: Vec<&str> = app.state.tracks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["/track/1", "/track/3", "/track/2"]);
+    }
+
+    #[test]
+    fn unusable_rates_are_ignored() {
+        let (mut app, handle) = app("Test Title");
+        handle.emit_signals();
+
+        for rate in [f64::INFINITY, f64::NAN, 1e300, 3.0] {
+            handle.push_event(PlayerEvent::PropertiesChanged {
+                changed: HashMap::from([("Rate".to_string(), MetadataValue::F64(rate))]),
+                invalidated: Vec::new(),
+            });
+            app.apply_events();
+            assert_eq!(app.state.rate, 1.0);
+        }
+
+        // Even a rate that slipped through can't break interpolation
+        app.state.status = Status::Playing;
+        app.state.position_sampled_at = Some(Instant::now() - Duration::from_secs(1));
+        app.state.rate = f64::INFINITY;
+        assert_eq!(app.state.current_position(), app.state.position);
+        assert!(screen_contains(&mut app, "Test Title"));
+    }
+
+    #[test]
+    fn losing_the_track_list_clears_the_queue() {
+        let (mut app, handle) = app("Test Title");
+        handle.emit_signals();
+        assert!(!app.state.tracks.is_empty());
+
+        handle.push_event(PlayerEvent::PropertiesChanged {
+            changed: HashMap::from([("CanEditTracks".to_string(), MetadataValue::Bool(true))]),
+            invalidated: Vec::new(),
+        });
+        app.apply_events();
+        assert!(app.state.caps.can_edit_tracks);
+
+        handle.push_event(PlayerEvent::PropertiesChanged {
+            changed: HashMap::from([("HasTrackList".to_string(), MetadataValue::Bool(false))]),
+            invalidated: Vec::new(),
+        });
+        app.apply_events();
+        assert!(!app.state.caps.has_track_list);
+        assert!(!app.state.caps.can_edit_tracks);
+        assert!(app.state.tracks.is_empty());
+    }
+
+    #[test]
+    fn disconnected_player_shows_hint() {
+        let (mut app, _) = app_with_state(PlayerState::default());
+
+        assert!(screen_contains(&mut app, "No MPRIS-compatible player found."));
+    }
+}
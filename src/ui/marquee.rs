@@ -0,0 +1,175 @@
+//! Fitting label text into its area: ellipsis and marquee scrolling.
+//!
+//! Widths are terminal columns, so wide characters such as CJK count twice.
+
+use std::time::Instant;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::MarqueeConfig;
+
+/// Scroll position of one label, restarted whenever its text changes
+pub struct Marquee {
+    text: String,
+    started: Instant,
+}
+
+impl Marquee {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    /// The `width` columns of `text` to show now
+    pub fn frame(&mut self, text: &str, width: usize, config: &MarqueeConfig) -> String {
+        if self.text != text {
+            *self = Self::new(text);
+        }
+
+        let looped = format!("{}{}", text, config.separator);
+        let cycle = looped.width();
+        if cycle == 0 || config.speed <= 0.0 {
+            return slice(text, 0, width);
+        }
+
+        let end = text.width().saturating_sub(width);
+        let offset = scroll_offset(self.started.elapsed().as_secs_f64(), end, cycle, config);
+        slice(&format!("{}{}", looped, text), offset, width)
+    }
+}
+
+/// Columns scrolled `elapsed` seconds in: rest at the start, scroll until the
+/// text's end (`end` columns in) is in view, rest again, then scroll on
+/// through the separator back to the start, `cycle` columns in all
+fn scroll_offset(elapsed: f64, end: usize, cycle: usize, config: &MarqueeConfig) -> usize {
+    let pause = config.pause_ms as f64 / 1000.0;
+    let to_end = end as f64 / config.speed;
+    let period = 2.0 * pause + cycle as f64 / config.speed;
+
+    let t = elapsed % period;
+    let moving = if t < pause {
+        0.0
+    } else if t < pause + to_end {
+        t - pause
+    } else if t < 2.0 * pause + to_end {
+        to_end
+    } else {
+        t - 2.0 * pause
+    };
+    (moving * config.speed) as usize % cycle
+}
+
+/// Whether `text` needs more than `width` columns
+pub fn overflows(text: &str, width: usize) -> bool {
+    text.width() > width
+}
+
+/// Cut `text` to `width` columns, ending with `…` if anything was cut
+pub fn ellipsize(text: &str, width: usize) -> String {
+    if !overflows(text, width) {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut cut = slice(text, 0, width - 1).trim_end().to_string();
+    cut.push('…');
+    cut
+}
+
+/// `width` columns of `text`, starting `skip` columns in.
+///
+/// Wide characters cut by either edge become spaces, so the result is
+/// exactly `width` columns unless `text` runs out.
+fn slice(text: &str, skip: usize, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let end = skip + width;
+
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        let next = column + w;
+        if next > end {
+            out.extend(std::iter::repeat_n(' ', end.saturating_sub(column.max(skip))));
+            break;
+        }
+        if column >= skip {
+            out.push(c);
+        } else if next > skip {
+            out.extend(std::iter::repeat_n(' ', next - skip));
+        }
+        column = next;
+        if column == end {
+            break;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config() -> MarqueeConfig {
+        MarqueeConfig {
+            speed: 1.0,
+            pause_ms: 1000,
+            separator: " | ".into(),
+        }
+    }
+
+    /// Frame of a marquee started `secs` ago
+    fn frame_after(secs: f64, text: &str, width: usize) -> String {
+        let mut marquee = Marquee::new(text);
+        marquee.started = Instant::now() - Duration::from_secs_f64(secs);
+        marquee.frame(text, width, &config())
+    }
+
+    #[test]
+    fn wide_characters_cut_at_either_edge_become_spaces() {
+        // 漢 and 字 are two columns each
+        assert_eq!(slice("漢字ab", 1, 3), " 字");
+        assert_eq!(slice("a漢b", 0, 2), "a ");
+        assert_eq!(slice("漢字漢", 1, 4), " 字 ");
+        for (skip, width) in [(0, 3), (1, 3), (1, 4), (3, 2)] {
+            assert_eq!(slice("漢字漢字", skip, width).width(), width);
+        }
+    }
+
+    #[test]
+    fn zero_width_is_empty() {
+        assert_eq!(slice("漢字", 0, 0), "");
+        assert_eq!(ellipsize("漢字", 0), "");
+        assert_eq!(frame_after(5.0, "abcdef", 0), "");
+    }
+
+    #[test]
+    fn ellipsis_fits_the_width() {
+        assert_eq!(ellipsize("short", 10), "short");
+        assert_eq!(ellipsize("漢字漢字", 5), "漢字…");
+        assert_eq!(ellipsize("漢字漢字", 4), "漢…");
+        assert_eq!(ellipsize("ab cdef", 4), "ab…");
+    }
+
+    #[test]
+    fn scrolling_pauses_at_both_ends_and_wraps() {
+        // "abcdefghij | " is 13 columns, and the end shows 6 columns in at width 4
+        let (end, cycle) = (6, 13);
+        let offset = |secs| scroll_offset(secs, end, cycle, &config());
+
+        assert_eq!(offset(0.5), 0);
+        assert_eq!(offset(4.5), 3);
+        assert_eq!(offset(7.5), 6);
+        assert_eq!(offset(8.5), 6);
+        assert_eq!(offset(11.5), 9);
+        assert_eq!(offset(14.5), 12);
+        assert_eq!(offset(15.5), 0);
+        assert_eq!(offset(19.5), 3);
+
+        assert_eq!(frame_after(0.5, "abcdefghij", 4), "abcd");
+        assert_eq!(frame_after(8.5, "abcdefghij", 4), "ghij");
+        assert_eq!(frame_after(13.5, "abcdefghij", 4), "| ab");
+    }
+}
@@ -0,0 +1,45 @@
+use ratatui::{layout::Rect, widgets::ListItem, Frame};
+
+use super::popup::ListPopup;
+use crate::action::Action;
+
+/// Right-click menu of actions, opened at the mouse position
+pub struct ContextMenu {
+    pub popup: ListPopup,
+    anchor: (u16, u16),
+    actions: Vec<Action>,
+}
+
+impl ContextMenu {
+    pub fn new(actions: &[Action], anchor: (u16, u16)) -> Self {
+        Self {
+            popup: ListPopup::new(0),
+            anchor,
+            actions: actions.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Action of an entry
+    pub fn action(&self, index: usize) -> Option<&Action> {
+        self.actions.get(index)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let labels: Vec<String> = self.actions.iter().map(label).collect();
+        let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 4;
+        let items = labels.into_iter().map(ListItem::new).collect();
+        self.popup.render_at(frame, area, self.anchor, "", items, width);
+    }
+}
+
+/// `toggle_shuffle` → ` Toggle shuffle`, `seek(+30s)` → ` Seek(+30s)`
+fn label(action: &Action) -> String {
+    let text = action.to_string().replace('_', " ");
+    let mut chars = text.chars();
+    let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+    format!(" {}{}", first, chars.as_str())
+}
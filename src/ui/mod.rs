@@ -1,4 +1,11 @@
 mod app;
+mod marquee;
+mod menu;
+mod open;
+mod picker;
+mod playlists;
+mod popup;
+mod terminal;
 mod widgets;
 
 pub use app::App;
@@ -0,0 +1,370 @@
+use crossterm::event::KeyCode;
+use directories::UserDirs;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+    Frame,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::popup::ListPopup;
+
+/// What the open prompt is currently taking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenMode {
+    /// A typed URI or path
+    Uri,
+    /// A file picked from a directory listing
+    Files,
+}
+
+/// What a key or mouse event did to the open prompt
+pub enum OpenInput {
+    Consumed,
+    /// Open this URI
+    Open(String),
+    Closed,
+}
+
+/// Entry in the file browser listing
+struct FileEntry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// Overlay that sends a URI or a local file to the player's `OpenUri`
+pub struct OpenPrompt {
+    pub popup: ListPopup,
+    mode: OpenMode,
+    /// The URI in `Uri` mode, the fuzzy filter in `Files` mode
+    input: String,
+    dir: PathBuf,
+    entries: Vec<FileEntry>,
+    /// Indices into `entries` that match the filter, best first
+    matches: Vec<usize>,
+    schemes: Vec<String>,
+    mime_types: Vec<String>,
+}
+
+impl OpenPrompt {
+    /// Open the prompt; the file browser starts in the music directory
+    pub fn new(mode: OpenMode, schemes: Vec<String>, mime_types: Vec<String>) -> Self {
+        let dir = UserDirs::new()
+            .and_then(|dirs| {
+                dirs.audio_dir()
+                    .map(Path::to_path_buf)
+                    .or_else(|| Some(dirs.home_dir().to_path_buf()))
+            })
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("/"));
+
+        let mut prompt = Self {
+            popup: ListPopup::new(0),
+            mode,
+            input: String::new(),
+            dir,
+            entries: Vec::new(),
+            matches: Vec::new(),
+            schemes,
+            mime_types,
+        };
+        prompt.read_dir();
+        prompt
+    }
+
+    /// Number of selectable entries
+    pub fn len(&self) -> usize {
+        match self.mode {
+            OpenMode::Uri => 1,
+            OpenMode::Files => self.matches.len(),
+        }
+    }
+
+    /// Handle a key: text edits the input, Tab switches modes, Up/Down move
+    pub fn handle_key(&mut self, code: KeyCode) -> OpenInput {
+        let len = self.len();
+        match code {
+            KeyCode::Esc => return OpenInput::Closed,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.mode = match self.mode {
+                    OpenMode::Uri => OpenMode::Files,
+                    OpenMode::Files => OpenMode::Uri,
+                };
+                self.input.clear();
+                self.apply_filter();
+            }
+            KeyCode::Up => self.popup.move_selection(-1, len),
+            KeyCode::Down => self.popup.move_selection(1, len),
+            KeyCode::Enter => {
+                if let Some(index) = self.popup.selected() {
+                    return self.choose(index);
+                }
+            }
+            KeyCode::Backspace => {
+                if self.input.pop().is_none() && self.mode == OpenMode::Files {
+                    self.go_up();
+                } else {
+                    self.apply_filter();
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.apply_filter();
+            }
+            _ => {}
+        }
+        OpenInput::Consumed
+    }
+
+    /// Act on an entry: open a typed URI or file, or enter a directory
+    pub fn choose(&mut self, index: usize) -> OpenInput {
+        match self.mode {
+            OpenMode::Uri => {
+                let input = self.input.trim();
+                if input.is_empty() {
+                    OpenInput::Consumed
+                } else {
+                    OpenInput::Open(input_to_uri(input))
+                }
+            }
+            OpenMode::Files => {
+                let Some(entry) = self.matches.get(index).map(|&i| &self.entries[i]) else {
+                    return OpenInput::Consumed;
+                };
+                if entry.is_dir {
+                    self.dir = entry.path.clone();
+                    self.input.clear();
+                    self.read_dir();
+                    OpenInput::Consumed
+                } else {
+                    OpenInput::Open(file_uri(&entry.path))
+                }
+            }
+        }
+    }
+
+    /// Move to the parent directory, keeping the one we left highlighted
+    fn go_up(&mut self) {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let left = std::mem::replace(&mut self.dir, parent);
+        self.read_dir();
+
+        if let Some(pos) = self.matches.iter().position(|&i| self.entries[i].path == left) {
+            self.popup.select(pos);
+        }
+    }
+
+    /// List `dir`: subdirectories first, then files the player can open
+    fn read_dir(&mut self) {
+        let mut entries: Vec<FileEntry> = fs::read_dir(&self.dir)
+            .map(|read| {
+                read.filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        let path = entry.path();
+                        // Follows symlinks, unlike DirEntry::file_type
+                        let is_dir = path.is_dir();
+                        let visible = !name.starts_with('.')
+                            && (is_dir || self.can_open(&path));
+                        visible.then_some(FileEntry { name, path, is_dir })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+
+        if let Some(parent) = self.dir.parent() {
+            entries.insert(0, FileEntry {
+                name: "..".into(),
+                path: parent.to_path_buf(),
+                is_dir: true,
+            });
+        }
+
+        self.entries = entries;
+        self.apply_filter();
+    }
+
+    /// Whether the player claims to handle this file's type.
+    ///
+    /// Players that don't list MIME types get every file.
+    fn can_open(&self, path: &Path) -> bool {
+        if self.mime_types.is_empty() {
+            return true;
+        }
+        mime_type(path).is_some_and(|mime| {
+            self.mime_types.iter().any(|supported| mime_matches(supported, mime))
+        })
+    }
+
+    /// Re-rank entries against the filter and reset the highlight
+    fn apply_filter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                if self.input.is_empty() {
+                    Some((0, i))
+                } else if entry.name == ".." {
+                    None
+                } else {
+                    fuzzy_score(&self.input, &entry.name).map(|score| (score, i))
+                }
+            })
+            .collect();
+
+        // Stable sort keeps directory order for equal scores
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.popup.select(0);
+    }
+
+    /// Render the prompt centered over `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let cursor = Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED));
+        let header = Line::from(vec![
+            Span::raw("> "),
+            Span::raw(self.input.clone()),
+            cursor,
+        ]);
+
+        let (title, items) = match self.mode {
+            OpenMode::Uri => {
+                let hint = if self.schemes.is_empty() {
+                    "Player does not open URIs".to_string()
+                } else {
+                    format!("Schemes: {}  (Tab: browse files)", self.schemes.join(", "))
+                };
+                (
+                    " Open URI ".to_string(),
+                    vec![ListItem::new(Span::styled(hint, Style::default().add_modifier(Modifier::DIM)))],
+                )
+            }
+            OpenMode::Files => {
+                let items = if self.matches.is_empty() {
+                    vec![ListItem::new("No matching files")]
+                } else {
+                    self.matches
+                        .iter()
+                        .map(|&i| {
+                            let entry = &self.entries[i];
+                            if entry.is_dir {
+                                ListItem::new(Span::styled(
+                                    format!("{}/", entry.name),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ))
+                            } else {
+                                ListItem::new(entry.name.clone())
+                            }
+                        })
+                        .collect()
+                };
+                (format!(" Open file: {} ", self.dir.display()), items)
+            }
+        };
+
+        self.popup.render_with_header(frame, area, &title, Some(header), items, 70);
+    }
+}
+
+/// Turn typed input into a URI, treating absolute and `~` paths as local files
+fn input_to_uri(input: &str) -> String {
+    if let Some(rest) = input.strip_prefix("~/") {
+        if let Some(dirs) = UserDirs::new() {
+            return file_uri(&dirs.home_dir().join(rest));
+        }
+    }
+    if input.starts_with('/') {
+        return file_uri(Path::new(input));
+    }
+    input.to_string()
+}
+
+/// `file://` URI for a local path, percent-encoding each component
+fn file_uri(path: &Path) -> String {
+    let encoded: Vec<String> = path
+        .to_string_lossy()
+        .split('/')
+        .map(|part| urlencoding::encode(part).into_owned())
+        .collect();
+    format!("file://{}", encoded.join("/"))
+}
+
+/// MIME type for common media file extensions
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let mime = match ext.as_str() {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "m4a" | "m4b" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "wma" => "audio/x-ms-wma",
+        "ape" => "audio/x-ape",
+        "mka" => "audio/x-matroska",
+        "mp4" | "m4v" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "avi" => "video/x-msvideo",
+        "mov" => "video/quicktime",
+        "m3u" | "m3u8" => "audio/x-mpegurl",
+        "pls" => "audio/x-scpls",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Compare MIME types, accepting `type/*` wildcards and `x-` prefixed subtypes
+fn mime_matches(supported: &str, mime: &str) -> bool {
+    let (Some((s_type, s_sub)), Some((m_type, m_sub))) = (supported.split_once('/'), mime.split_once('/')) else {
+        return false;
+    };
+    if !s_type.eq_ignore_ascii_case(m_type) {
+        return false;
+    }
+    let strip = |sub: &str| sub.strip_prefix("x-").unwrap_or(sub).to_ascii_lowercase();
+    s_sub == "*" || strip(s_sub) == strip(m_sub)
+}
+
+/// Score `candidate` against a fuzzy `query`, or None if it doesn't match.
+///
+/// Every query character must appear in order; consecutive matches and
+/// matches at the start of a word score higher.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = candidate[pos..].iter().position(|&c| c == q)? + pos;
+
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], ' ' | '-' | '_' | '.' | '/') {
+            score += 10;
+        }
+
+        last_match = Some(found);
+        pos = found + 1;
+    }
+
+    // Prefer shorter names among equally good matches
+    Some(score * 100 - candidate.len() as i64)
+}
@@ -0,0 +1,58 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+    Frame,
+};
+
+use super::popup::ListPopup;
+use crate::mpris_client::PlayerSummary;
+
+/// Popup list for choosing the active player
+pub struct PlayerPicker {
+    pub popup: ListPopup,
+}
+
+impl PlayerPicker {
+    /// Open the picker with the active player pre-selected
+    pub fn new(players: &[PlayerSummary], active: Option<&str>) -> Self {
+        let selected = active
+            .and_then(|name| players.iter().position(|p| p.bus_name == name))
+            .unwrap_or(0);
+
+        Self {
+            popup: ListPopup::new(selected),
+        }
+    }
+
+    /// Render the popup centered over `area`
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        players: &[PlayerSummary],
+        active: Option<&str>,
+        pinned: bool,
+    ) {
+        let items: Vec<ListItem> = if players.is_empty() {
+            vec![ListItem::new("No players found")]
+        } else {
+            players
+                .iter()
+                .map(|p| {
+                    let marker = if Some(p.bus_name.as_str()) == active { "● " } else { "  " };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(marker),
+                        Span::styled(p.identity.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                        Span::raw(format!(" {} ", p.status.icon())),
+                        Span::raw(p.title.clone()),
+                    ]))
+                })
+                .collect()
+        };
+
+        let title = if pinned { " Players (pinned) " } else { " Players " };
+        self.popup.render(frame, area, title, items, 60);
+    }
+}
@@ -0,0 +1,64 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+    Frame,
+};
+
+use super::popup::ListPopup;
+use crate::mpris_client::Playlist;
+
+/// Popup list for activating one of the player's playlists
+pub struct PlaylistBrowser {
+    pub popup: ListPopup,
+}
+
+impl PlaylistBrowser {
+    /// Open the browser with the active playlist pre-selected
+    pub fn new(playlists: &[Playlist], active: Option<&Playlist>) -> Self {
+        let selected = active
+            .and_then(|active| playlists.iter().position(|p| p.id == active.id))
+            .unwrap_or(0);
+
+        Self {
+            popup: ListPopup::new(selected),
+        }
+    }
+
+    /// Render the popup centered over `area`
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        playlists: &[Playlist],
+        active: Option<&Playlist>,
+        icon: &str,
+    ) {
+        let blank_icon = " ".repeat(icon.chars().count());
+        let items: Vec<ListItem> = if playlists.is_empty() {
+            vec![ListItem::new("No playlists")]
+        } else {
+            playlists
+                .iter()
+                .map(|p| {
+                    let is_active = active.is_some_and(|a| a.id == p.id);
+                    let marker = if is_active { "● " } else { "  " };
+                    let name_style = if is_active {
+                        Style::default().add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(marker),
+                        Span::raw(if p.icon.is_some() { icon } else { &blank_icon }),
+                        Span::raw(" "),
+                        Span::styled(p.name.clone(), name_style),
+                    ]))
+                })
+                .collect()
+        };
+
+        self.popup.render(frame, area, " Playlists ", items, 60);
+    }
+}
@@ -0,0 +1,187 @@
+use crossterm::event::{KeyCode, MouseButton, MouseEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+/// What a key or mouse event did to a popup
+pub enum PopupInput {
+    /// Handled inside the popup (or ignored)
+    Consumed,
+    /// An entry was chosen
+    Chosen(usize),
+    /// The popup should close
+    Closed,
+}
+
+/// Centered popup list shared by the overlays
+pub struct ListPopup {
+    list_state: ListState,
+    /// Inner list area from the last render, for mouse hit-testing
+    list_area: Option<Rect>,
+    /// Outer popup area from the last render
+    popup_area: Option<Rect>,
+}
+
+impl ListPopup {
+    /// Create a popup with `selected` highlighted
+    pub fn new(selected: usize) -> Self {
+        Self {
+            list_state: ListState::default().with_selected(Some(selected)),
+            list_area: None,
+            popup_area: None,
+        }
+    }
+
+    /// Index of the highlighted entry
+    pub fn selected(&self) -> Option<usize> {
+        self.list_state.selected()
+    }
+
+    /// Highlight an entry
+    pub fn select(&mut self, index: usize) {
+        self.list_state.select(Some(index));
+    }
+
+    /// Move the highlight, wrapping around at either end
+    pub fn move_selection(&mut self, delta: isize, len: usize) {
+        if len == 0 {
+            self.list_state.select(None);
+            return;
+        }
+
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).rem_euclid(len as isize) as usize;
+        self.list_state.select(Some(next));
+    }
+
+    /// Whether a position lies inside the popup
+    pub fn contains(&self, col: u16, row: u16) -> bool {
+        self.popup_area
+            .is_some_and(|area| area.contains((col, row).into()))
+    }
+
+    /// Entry index under a position, if any
+    pub fn entry_at(&self, col: u16, row: u16, len: usize) -> Option<usize> {
+        let area = self.list_area?;
+        if !area.contains((col, row).into()) {
+            return None;
+        }
+
+        let index = self.list_state.offset() + (row - area.y) as usize;
+        (index < len).then_some(index)
+    }
+
+    /// Navigate with arrows/j/k, choose with Enter, close with Escape or q
+    pub fn handle_key(&mut self, code: KeyCode, len: usize) -> PopupInput {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1, len),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1, len),
+            KeyCode::Enter => match self.selected() {
+                Some(index) if index < len => return PopupInput::Chosen(index),
+                _ => return PopupInput::Closed,
+            },
+            KeyCode::Esc | KeyCode::Char('q') => return PopupInput::Closed,
+            _ => {}
+        }
+        PopupInput::Consumed
+    }
+
+    /// Scroll to move, click an entry to choose it, click outside to close
+    pub fn handle_mouse(&mut self, kind: MouseEventKind, col: u16, row: u16, len: usize) -> PopupInput {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if !self.contains(col, row) {
+                    return PopupInput::Closed;
+                }
+                if let Some(index) = self.entry_at(col, row, len) {
+                    return PopupInput::Chosen(index);
+                }
+            }
+            MouseEventKind::ScrollUp => self.move_selection(-1, len),
+            MouseEventKind::ScrollDown => self.move_selection(1, len),
+            _ => {}
+        }
+        PopupInput::Consumed
+    }
+
+    /// Render the popup centered over `area`, sized to fit `items`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, title: &str, items: Vec<ListItem>, width: u16) {
+        self.render_with_header(frame, area, title, None, items, width);
+    }
+
+    /// Render the popup with an optional line (such as a text input) above the list
+    pub fn render_with_header(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        header: Option<Line>,
+        items: Vec<ListItem>,
+        width: u16,
+    ) {
+        let header_height = u16::from(header.is_some());
+        let height = (items.len() as u16).max(1).saturating_add(2 + header_height);
+        let popup = centered_rect(area, area.width.saturating_sub(4).min(width), height);
+        self.draw(frame, popup, title, header, items);
+    }
+
+    /// Render the popup with its top left corner at `anchor`, shifted to stay inside `area`
+    pub fn render_at(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        anchor: (u16, u16),
+        title: &str,
+        items: Vec<ListItem>,
+        width: u16,
+    ) {
+        let width = width.min(area.width);
+        let height = (items.len() as u16).max(1).saturating_add(2).min(area.height);
+        let x = anchor.0.min(area.right().saturating_sub(width)).max(area.x);
+        let y = anchor.1.min(area.bottom().saturating_sub(height)).max(area.y);
+        self.draw(frame, Rect { x, y, width, height }, title, None, items);
+    }
+
+    fn draw(&mut self, frame: &mut Frame, popup: Rect, title: &str, header: Option<Line>, items: Vec<ListItem>) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title);
+        let mut inner = block.inner(popup);
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(block, popup);
+
+        if let Some(header) = header {
+            let header_area = Rect { height: inner.height.min(1), ..inner };
+            frame.render_widget(Paragraph::new(header), header_area);
+            inner.y += header_area.height;
+            inner.height -= header_area.height;
+        }
+
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, inner, &mut self.list_state);
+
+        self.popup_area = Some(popup);
+        self.list_area = Some(inner);
+    }
+}
+
+/// Rect of the given size centered in `area`
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(height.min(area.height))])
+        .flex(Flex::Center)
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(width.min(area.width))])
+        .flex(Flex::Center)
+        .split(vertical[0])[0]
+}
@@ -0,0 +1,93 @@
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP};
+use signal_hook::{flag, low_level};
+use anyhow::anyhow;
+use std::io;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::log;
+
+/// Enter raw mode, the alternate screen and mouse capture
+pub fn setup() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Undo everything `setup` did
+pub fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show)
+}
+
+/// Restore the terminal before the default panic message is printed.
+///
+/// Must be called from the thread running the UI. Panics on other threads
+/// leave the UI running, so they go to the log instead of the screen.
+pub fn install_panic_hook() {
+    let ui_thread = thread::current().id();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if thread::current().id() == ui_thread {
+            let _ = restore();
+            default_hook(info);
+        } else {
+            log::error(&anyhow!("Background thread panicked: {}", info));
+        }
+    }));
+}
+
+/// Stop the process like the shell's Ctrl+Z would, restoring the terminal
+/// around the stop
+pub fn suspend() -> io::Result<()> {
+    restore()?;
+    low_level::emulate_default_handler(SIGTSTP)?;
+    // Execution continues here after SIGCONT
+    setup()
+}
+
+/// Flags set by signal handlers, checked from the main loop
+pub struct Signals {
+    quit: Arc<AtomicBool>,
+    suspend: Arc<AtomicBool>,
+    resumed: Arc<AtomicBool>,
+}
+
+impl Signals {
+    /// Register handlers for termination, suspend and resume signals
+    pub fn register() -> io::Result<Self> {
+        let quit = Arc::new(AtomicBool::new(false));
+        let suspend = Arc::new(AtomicBool::new(false));
+        let resumed = Arc::new(AtomicBool::new(false));
+
+        for signal in [SIGTERM, SIGINT, SIGHUP] {
+            flag::register(signal, Arc::clone(&quit))?;
+        }
+        flag::register(SIGTSTP, Arc::clone(&suspend))?;
+        flag::register(SIGCONT, Arc::clone(&resumed))?;
+
+        Ok(Self { quit, suspend, resumed })
+    }
+
+    /// A termination signal arrived
+    pub fn quit_requested(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    /// A SIGTSTP arrived since the last call
+    pub fn take_suspend(&self) -> bool {
+        self.suspend.swap(false, Ordering::Relaxed)
+    }
+
+    /// A SIGCONT arrived since the last call
+    pub fn take_resumed(&self) -> bool {
+        self.resumed.swap(false, Ordering::Relaxed)
+    }
+}
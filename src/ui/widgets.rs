@@ -2,16 +2,18 @@ use ratatui::{
     layout::{Alignment as RatatuiAlignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::Line,
-    widgets::Paragraph,
+    widgets::{List, ListItem, ListState, Paragraph},
     Frame,
 };
 
 use crate::config::{
     Alignment, Direction as LayoutDirection, Layout as LayoutConfig, LayoutChild,
-    LabelConfig, ProgressConfig, VolumeConfig, WidgetConfig,
+    LabelConfig, Overflow, ProgressConfig, QueueConfig, VolumeConfig, WidgetConfig,
 };
+use crate::action::Action;
 use crate::cover::CoverArtLoader;
 use crate::mpris_client::{format_duration, PlayerState};
+use super::marquee::{self, Marquee};
 use std::collections::HashMap;
 
 impl From<LayoutDirection> for Direction {
@@ -23,11 +25,73 @@ impl From<LayoutDirection> for Direction {
     }
 }
 
-/// Track areas where interactive widgets are rendered
-#[derive(Default, Clone, Copy)]
-pub struct WidgetAreas {
-    pub controls: Option<Rect>,
-    pub progress: Option<Rect>,
+impl From<Alignment> for RatatuiAlignment {
+    fn from(a: Alignment) -> Self {
+        match a {
+            Alignment::Left => RatatuiAlignment::Left,
+            Alignment::Center => RatatuiAlignment::Center,
+            Alignment::Right => RatatuiAlignment::Right,
+        }
+    }
+}
+
+/// What an interactive widget does with the mouse
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetKind {
+    /// Click to seek
+    Progress,
+    /// Click to set the volume
+    Volume,
+    Button(Action),
+    /// Click to play a track, scroll to move the selection
+    Queue,
+}
+
+/// Interactive part of a rendered widget
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub area: Rect,
+    /// Widget name from the config
+    pub id: String,
+    pub kind: WidgetKind,
+}
+
+/// Every interactive widget drawn in the last frame
+#[derive(Default, Clone)]
+pub struct HitMap {
+    pub hits: Vec<Hit>,
+}
+
+impl HitMap {
+    /// Widget at a screen position
+    pub fn at(&self, col: u16, row: u16) -> Option<&Hit> {
+        self.hits.iter().find(|hit| hit.area.contains((col, row).into()))
+    }
+}
+
+/// Widget state kept between frames
+pub struct WidgetStates {
+    pub cover_loader: CoverArtLoader,
+    /// Selection and scroll offset of the queue widget
+    pub queue: ListState,
+    /// Scroll positions of marquee labels, by widget name
+    pub marquees: HashMap<String, Marquee>,
+    /// Last known mouse position, for hover styles
+    pub pointer: Option<(u16, u16)>,
+    /// Button held down with the mouse
+    pub pressed: Option<Hit>,
+}
+
+impl WidgetStates {
+    pub fn new() -> Self {
+        Self {
+            cover_loader: CoverArtLoader::new(),
+            queue: ListState::default(),
+            marquees: HashMap::new(),
+            pointer: None,
+            pressed: None,
+        }
+    }
 }
 
 /// Render the layout to the frame
@@ -37,12 +101,13 @@ pub fn render_layout(
     layout: &LayoutConfig,
     widgets: &HashMap<String, WidgetConfig>,
     state: &PlayerState,
-    cover_loader: &mut CoverArtLoader,
-) -> WidgetAreas {
-    let mut widget_areas = WidgetAreas::default();
+    message: &str,
+    widget_states: &mut WidgetStates,
+) -> HitMap {
+    let mut hits = HitMap::default();
 
     if layout.children.is_empty() {
-        return widget_areas;
+        return hits;
     }
 
     let direction: Direction = layout.direction.into();
@@ -73,7 +138,14 @@ pub fn render_layout(
                                 Constraint::Length(1)
                             }
                         }
-                        WidgetConfig::Button(_) => Constraint::Length(1),
+                        WidgetConfig::Button(_) => {
+                            if direction == Direction::Horizontal {
+                                Constraint::Min(1)
+                            } else {
+                                Constraint::Length(1)
+                            }
+                        }
+                        WidgetConfig::Queue(_) => Constraint::Min(3),
                         WidgetConfig::CoverArt(_) => {
                             // Square proportions: width = height * 2 (terminal chars are ~2:1)
                             // Cap at reasonable size to not crush other elements
@@ -129,86 +201,102 @@ pub fn render_layout(
         match child {
             LayoutChild::Widget(name) => {
                 if let Some(widget_config) = widgets.get(name) {
-                    render_widget(frame, chunks[i], widget_config, state, cover_loader);
-
-                    // Track interactive widget areas
-                    if name == "controls" {
-                        widget_areas.controls = Some(chunks[i]);
-                    } else if name == "progress" {
-                        widget_areas.progress = Some(chunks[i]);
+                    let hit = render_widget(frame, chunks[i], name, widget_config, state, message, widget_states);
+                    if let Some((area, kind)) = hit {
+                        hits.hits.push(Hit { area, id: name.clone(), kind });
                     }
                 }
             }
             LayoutChild::Container(nested) => {
-                let nested_areas = render_layout(frame, chunks[i], nested, widgets, state, cover_loader);
-                // Merge nested areas
-                if nested_areas.controls.is_some() {
-                    widget_areas.controls = nested_areas.controls;
-                }
-                if nested_areas.progress.is_some() {
-                    widget_areas.progress = nested_areas.progress;
-                }
+                let nested = render_layout(frame, chunks[i], nested, widgets, state, message, widget_states);
+                hits.hits.extend(nested.hits);
             }
         }
     }
 
-    widget_areas
+    hits
 }
 
-/// Render a single widget
+/// Render a single widget, returning its interactive area if it has one
 fn render_widget(
     frame: &mut Frame,
     area: Rect,
+    name: &str,
     config: &WidgetConfig,
     state: &PlayerState,
-    cover_loader: &mut CoverArtLoader,
-) {
+    message: &str,
+    widget_states: &mut WidgetStates,
+) -> Option<(Rect, WidgetKind)> {
     match config {
-        WidgetConfig::Label(cfg) => render_label(frame, area, cfg, state),
-        WidgetConfig::Progress(cfg) => render_progress(frame, area, cfg, state),
-        WidgetConfig::Volume(cfg) => render_volume(frame, area, cfg, state),
-        WidgetConfig::Button(cfg) => render_button(frame, area, cfg, state),
-        WidgetConfig::CoverArt(cfg) => render_cover_art(frame, area, cfg, state, cover_loader),
-        WidgetConfig::Empty(_) => {}
+        WidgetConfig::Label(cfg) => {
+            let text = cfg.text.render(state, message);
+            let text = fit_label(name, text, area.width as usize, cfg, &mut widget_states.marquees);
+            render_label(frame, area, text, cfg);
+            None
+        }
+        WidgetConfig::Progress(cfg) => Some((render_progress(frame, area, cfg, state), WidgetKind::Progress)),
+        WidgetConfig::Volume(cfg) => Some((render_volume(frame, area, cfg, state), WidgetKind::Volume)),
+        WidgetConfig::Button(cfg) => {
+            let pressed = widget_states
+                .pressed
+                .as_ref()
+                .is_some_and(|hit| hit.id == name && hit.area == area);
+            let hovered = widget_states.pointer.is_some_and(|pos| area.contains(pos.into()));
+            render_button(frame, area, cfg, state, message, pressed, hovered);
+            Some((area, WidgetKind::Button(cfg.action.clone())))
+        }
+        WidgetConfig::CoverArt(cfg) => {
+            render_cover_art(frame, area, cfg, state, &mut widget_states.cover_loader);
+            None
+        }
+        WidgetConfig::Queue(cfg) => {
+            render_queue(frame, area, cfg, state, &mut widget_states.queue);
+            Some((area, WidgetKind::Queue))
+        }
+        WidgetConfig::Empty(_) => None,
     }
 }
 
-/// Substitute variables in text
-fn substitute_vars(text: &str, state: &PlayerState) -> String {
-    text.replace("$title", &state.title)
-        .replace("$artists", &state.artists)
-        .replace("$artist", &state.artists)
-        .replace("$album", &state.album)
-        .replace("$status-icon", state.status.icon())
-        .replace("$status", &format!("{:?}", state.status))
-        .replace("$position", &format_duration(state.position))
-        .replace("$length", &format_duration(state.length))
-        .replace("$volume", &format!("{}%", (state.volume * 100.0) as u8))
-        .replace("$player", &state.player_name)
+/// Apply the label's overflow handling to text wider than `width`
+fn fit_label(
+    name: &str,
+    text: String,
+    width: usize,
+    config: &LabelConfig,
+    marquees: &mut HashMap<String, Marquee>,
+) -> String {
+    if !marquee::overflows(&text, width) {
+        // Starts from the beginning if the text grows too long again
+        marquees.remove(name);
+        return text;
+    }
+
+    match config.overflow {
+        Overflow::Clip => text,
+        Overflow::Ellipsis => marquee::ellipsize(&text, width),
+        Overflow::Marquee => marquees
+            .entry(name.to_string())
+            .or_insert_with(|| Marquee::new(&text))
+            .frame(&text, width, &config.marquee),
+    }
 }
 
 /// Render a label widget
-fn render_label(frame: &mut Frame, area: Rect, config: &LabelConfig, state: &PlayerState) {
-    let text = substitute_vars(&config.text, state);
-    let alignment = match config.align {
-        Alignment::Left => RatatuiAlignment::Left,
-        Alignment::Center => RatatuiAlignment::Center,
-        Alignment::Right => RatatuiAlignment::Right,
-    };
-
+fn render_label(frame: &mut Frame, area: Rect, text: String, config: &LabelConfig) {
     let style = build_style(&config.style);
 
     let paragraph = Paragraph::new(text)
-        .alignment(alignment)
+        .alignment(config.align.into())
         .style(style);
 
     frame.render_widget(paragraph, area);
 }
 
-/// Render a progress bar widget
-fn render_progress(frame: &mut Frame, area: Rect, config: &ProgressConfig, state: &PlayerState) {
+/// Render a progress bar widget, returning the area of the bar itself
+fn render_progress(frame: &mut Frame, area: Rect, config: &ProgressConfig, state: &PlayerState) -> Rect {
+    let position = state.current_position();
     let progress = if state.length.as_secs() > 0 {
-        (state.position.as_secs_f64() / state.length.as_secs_f64()).clamp(0.0, 1.0)
+        (position.as_secs_f64() / state.length.as_secs_f64()).clamp(0.0, 1.0)
     } else {
         0.0
     };
@@ -223,25 +311,27 @@ fn render_progress(frame: &mut Frame, area: Rect, config: &ProgressConfig, state
             ])
             .split(area);
 
-        let pos_text = format_duration(state.position);
+        let pos_text = format_duration(position);
         frame.render_widget(
             Paragraph::new(pos_text).alignment(RatatuiAlignment::Right),
             chunks[0],
         );
 
-        render_progress_bar(frame, chunks[1], progress, config);
+        render_progress_bar(frame, chunks[1], progress, config, state.caps.can_seek);
 
         let len_text = format_duration(state.length);
         frame.render_widget(
             Paragraph::new(len_text).alignment(RatatuiAlignment::Left),
             chunks[2],
         );
+        chunks[1]
     } else {
-        render_progress_bar(frame, area, progress, config);
+        render_progress_bar(frame, area, progress, config, state.caps.can_seek);
+        area
     }
 }
 
-fn render_progress_bar(frame: &mut Frame, area: Rect, progress: f64, config: &ProgressConfig) {
+fn render_progress_bar(frame: &mut Frame, area: Rect, progress: f64, config: &ProgressConfig, enabled: bool) {
     let width = area.width as usize;
     let filled = (progress * width as f64) as usize;
     let empty = width.saturating_sub(filled);
@@ -254,11 +344,16 @@ fn render_progress_bar(frame: &mut Frame, area: Rect, progress: f64, config: &Pr
         bar.push(config.empty_char);
     }
 
-    let style = build_style(&config.style);
+    let style = if enabled {
+        build_style(&config.style)
+    } else {
+        build_style(&config.disabled_style)
+    };
     frame.render_widget(Paragraph::new(bar).style(style), area);
 }
 
-fn render_volume(frame: &mut Frame, area: Rect, config: &VolumeConfig, state: &PlayerState) {
+/// Render a volume widget, returning the area of the bar itself
+fn render_volume(frame: &mut Frame, area: Rect, config: &VolumeConfig, state: &PlayerState) -> Rect {
     let volume = state.volume.clamp(0.0, 1.0);
 
     if config.show_percentage {
@@ -276,13 +371,15 @@ fn render_volume(frame: &mut Frame, area: Rect, config: &VolumeConfig, state: &P
             chunks[0],
         );
 
-        render_volume_bar(frame, chunks[1], volume, config);
+        render_volume_bar(frame, chunks[1], volume, config, state.caps.can_control);
+        chunks[1]
     } else {
-        render_volume_bar(frame, area, volume, config);
+        render_volume_bar(frame, area, volume, config, state.caps.can_control);
+        area
     }
 }
 
-fn render_volume_bar(frame: &mut Frame, area: Rect, volume: f64, config: &VolumeConfig) {
+fn render_volume_bar(frame: &mut Frame, area: Rect, volume: f64, config: &VolumeConfig, enabled: bool) {
     let width = area.width as usize;
     let filled = (volume * width as f64) as usize;
     let empty = width.saturating_sub(filled);
@@ -295,7 +392,11 @@ fn render_volume_bar(frame: &mut Frame, area: Rect, volume: f64, config: &Volume
         bar.push(config.empty_char);
     }
 
-    let style = build_style(&config.style);
+    let style = if enabled {
+        build_style(&config.style)
+    } else {
+        build_style(&config.disabled_style)
+    };
     frame.render_widget(Paragraph::new(bar).style(style), area);
 }
 
@@ -304,9 +405,22 @@ fn render_button(
     area: Rect,
     config: &crate::config::ButtonConfig,
     state: &PlayerState,
+    message: &str,
+    pressed: bool,
+    hovered: bool,
 ) {
-    let text = substitute_vars(&config.text, state);
-    let style = build_style(&config.style);
+    let text = config.text.render(state, message);
+    let hover_style = config.hover_style.as_ref().unwrap_or(&config.style);
+    let style = if !action_supported(&config.action, state) {
+        &config.disabled_style
+    } else if pressed {
+        config.pressed_style.as_ref().unwrap_or(hover_style)
+    } else if hovered {
+        hover_style
+    } else {
+        &config.style
+    };
+    let style = build_style(style);
 
     let paragraph = Paragraph::new(text)
         .alignment(RatatuiAlignment::Center)
@@ -315,6 +429,69 @@ fn render_button(
     frame.render_widget(paragraph, area);
 }
 
+/// Render the player's TrackList as a scrollable list
+fn render_queue(frame: &mut Frame, area: Rect, config: &QueueConfig, state: &PlayerState, list_state: &mut ListState) {
+    if state.tracks.is_empty() {
+        let text = if state.caps.has_track_list { "Queue is empty" } else { "[No Queue]" };
+        frame.render_widget(Paragraph::new(text).alignment(RatatuiAlignment::Center), area);
+        return;
+    }
+
+    let style = build_style(&config.style);
+    let current_style = build_style(&config.current_style);
+
+    let items: Vec<ListItem> = state
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let text = config.text.render(track, i);
+            let is_current = state.track_id.as_deref() == Some(track.id.as_str());
+            ListItem::new(text).style(if is_current { current_style } else { style })
+        })
+        .collect();
+
+    // Keep the selection in range as the list changes
+    let last = state.tracks.len() - 1;
+    match list_state.selected() {
+        Some(i) if i > last => list_state.select(Some(last)),
+        None => {
+            let current = state
+                .tracks
+                .iter()
+                .position(|t| state.track_id.as_deref() == Some(t.id.as_str()));
+            list_state.select(Some(current.unwrap_or(0)));
+        }
+        _ => {}
+    }
+
+    let list = List::new(items).highlight_style(build_style(&config.selected_style));
+    frame.render_stateful_widget(list, area, list_state);
+}
+
+/// Whether the connected player supports a button's action
+fn action_supported(action: &Action, state: &PlayerState) -> bool {
+    let caps = &state.caps;
+    match action {
+        Action::Toggle => caps.can_play || caps.can_pause,
+        Action::Next => caps.can_go_next,
+        Action::Prev => caps.can_go_previous,
+        Action::Seek(_) | Action::SeekPercent(_) => caps.can_seek,
+        Action::Volume(_)
+        | Action::SetVolume(_)
+        | Action::ToggleShuffle
+        | Action::CycleLoop
+        | Action::RateUp
+        | Action::RateDown
+        | Action::RateReset => caps.can_control,
+        Action::QueuePlay | Action::QueueRemove => caps.has_track_list,
+        Action::Raise => caps.can_raise,
+        Action::QuitPlayer => caps.can_quit,
+        Action::ToggleFullscreen => caps.can_set_fullscreen,
+        _ => true,
+    }
+}
+
 fn render_cover_art(frame: &mut Frame, area: Rect, config: &crate::config::CoverArtConfig, state: &PlayerState, cover_loader: &mut CoverArtLoader) {
     if area.width == 0 || area.height == 0 {
         return;
@@ -460,7 +637,7 @@ fn render_image_halfblocks(img: &image::DynamicImage, target_width: usize, targe
     lines
 }
 
-fn build_style(config: &crate::config::StyleConfig) -> Style {
+pub fn build_style(config: &crate::config::StyleConfig) -> Style {
     let mut style = Style::default();
 
     if let Some(ref fg) = config.fg {
@@ -485,6 +662,9 @@ fn build_style(config: &crate::config::StyleConfig) -> Style {
     if config.underline {
         modifier |= Modifier::UNDERLINED;
     }
+    if config.dim {
+        modifier |= Modifier::DIM;
+    }
 
     style.add_modifier(modifier)
 }